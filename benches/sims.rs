@@ -0,0 +1,12 @@
+// runs `rs-automata bench` against the release binary, forwarding any extra
+// arguments, e.g. `cargo bench -- --bounds 32,64 --json`.
+use std::process::Command;
+
+fn main() {
+    let status = Command::new(env!("CARGO_BIN_EXE_rs-automata"))
+        .arg("bench")
+        .args(std::env::args().skip(1).filter(|arg| arg != "--bench"))
+        .status()
+        .expect("running rs-automata bench");
+    std::process::exit(status.code().unwrap_or(1));
+}
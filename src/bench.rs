@@ -0,0 +1,174 @@
+use bevy::tasks::TaskPoolBuilder;
+
+use crate::{
+    cells,
+    cli::{parse_list, parse_value},
+    examples,
+};
+
+struct BenchOptions {
+    bounds: Vec<i32>,
+    threads: Vec<usize>,
+    ticks: u32,
+    json: bool,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        let cpus = num_cpus::get();
+        let mut threads: Vec<usize> = (0..)
+            .map(|power| 1 << power)
+            .take_while(|count| *count < cpus)
+            .collect();
+        threads.push(cpus);
+
+        Self {
+            bounds: vec![32, 64, 128, 256],
+            threads,
+            ticks: 10,
+            json: false,
+        }
+    }
+}
+
+impl BenchOptions {
+    fn parse(args: &[String]) -> Self {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => options.json = true,
+                "--ticks" => options.ticks = parse_value(arg, args.next()),
+                "--bounds" => options.bounds = parse_list(arg, args.next()),
+                "--threads" => options.threads = parse_list(arg, args.next()),
+                _ => panic!(
+                    "unknown argument `{}`, expected --json, --ticks N, --bounds A,B or --threads A,B",
+                    arg
+                ),
+            }
+        }
+        options
+    }
+}
+
+struct BenchResult {
+    sim: String,
+    example: String,
+    bounds: i32,
+    threads: usize,
+    ns_per_cell_tick: f64,
+    peak_memory_kb: Option<u64>,
+}
+
+// linux only: writing 5 to clear_refs resets the peak resident set size.
+fn reset_peak_memory() {
+    let _ = std::fs::write("/proc/self/clear_refs", "5");
+}
+
+fn peak_memory_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+pub fn run(args: &[String]) {
+    let options = BenchOptions::parse(args);
+    let examples = examples::default_examples();
+    let sim_count = cells::default_sims().len();
+
+    let mut results = vec![];
+    for sim_idx in 0..sim_count {
+        for example in examples.iter() {
+            for bounds in options.bounds.iter().copied() {
+                for threads in options.threads.iter().copied() {
+                    let (name, mut sim) = cells::default_sims().swap_remove(sim_idx);
+                    let capabilities = sim.capabilities();
+                    if capabilities.check(&example.rule, bounds).is_err() {
+                        break;
+                    }
+                    if !capabilities.multi_threaded && threads != options.threads[0] {
+                        break;
+                    }
+
+                    let task_pool = TaskPoolBuilder::new().num_threads(threads).build();
+                    reset_peak_memory();
+                    sim.set_bounds(bounds);
+                    sim.spawn_noise(&example.rule);
+
+                    // the time is divided by the world's volume times the
+                    // generations advanced, which is the same for every
+                    // simulator, so sparse ones aren't charged per live cell.
+                    let volume = (bounds as u64).pow(3);
+                    let mut cell_ticks = 0;
+                    let mut elapsed = std::time::Duration::ZERO;
+                    for _ in 0..options.ticks {
+                        let t0 = std::time::Instant::now();
+                        sim.update(&example.rule, &task_pool);
+                        elapsed += t0.elapsed();
+                        cell_ticks += volume * sim.generations_per_update();
+                    }
+
+                    results.push(BenchResult {
+                        sim: name,
+                        example: example.name.clone(),
+                        bounds,
+                        threads,
+                        ns_per_cell_tick: elapsed.as_nanos() as f64 / cell_ticks.max(1) as f64,
+                        peak_memory_kb: peak_memory_kb(),
+                    });
+                    if !options.json {
+                        print_row(results.last().expect("getting result"), results.len() == 1);
+                    }
+                }
+            }
+        }
+    }
+
+    if options.json {
+        println!("{}", to_json(&results));
+    }
+}
+
+fn print_row(result: &BenchResult, header: bool) {
+    if header {
+        println!(
+            "{:<26} {:<26} {:>6} {:>7} {:>14} {:>12}",
+            "simulator", "example", "bounds", "threads", "ns/cell/tick", "peak KiB"
+        );
+    }
+    println!(
+        "{:<26} {:<26} {:>6} {:>7} {:>14.2} {:>12}",
+        result.sim,
+        result.example,
+        result.bounds,
+        result.threads,
+        result.ns_per_cell_tick,
+        result
+            .peak_memory_kb
+            .map_or("-".to_string(), |kb| kb.to_string()),
+    );
+}
+
+fn json_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn to_json(results: &[BenchResult]) -> String {
+    let rows: Vec<String> = results
+        .iter()
+        .map(|result| {
+            format!(
+                "  {{\"sim\": {}, \"example\": {}, \"bounds\": {}, \"threads\": {}, \"ns_per_cell_tick\": {:.3}, \"peak_memory_kb\": {}}}",
+                json_string(&result.sim),
+                json_string(&result.example),
+                result.bounds,
+                result.threads,
+                result.ns_per_cell_tick,
+                result
+                    .peak_memory_kb
+                    .map_or("null".to_string(), |kb| kb.to_string()),
+            )
+        })
+        .collect();
+    format!("[\n{}\n]", rows.join(",\n"))
+}
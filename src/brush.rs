@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{cells::Sims, utils};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BrushShape {
+    Sphere,
+    Cube,
+}
+
+pub struct Brush {
+    pub shape: BrushShape,
+    pub radius: i32,
+    pub state: u8,
+    // cell under the cursor and the empty cell in front of it.
+    pub hovered: Option<(IVec3, IVec3)>,
+}
+
+impl Brush {
+    pub fn new() -> Self {
+        Self {
+            shape: BrushShape::Sphere,
+            radius: 1,
+            state: u8::MAX,
+            hovered: None,
+        }
+    }
+
+    pub fn cells(&self, center: IVec3, bounds: i32) -> Vec<IVec3> {
+        let r = self.radius;
+        let mut cells = vec![];
+        for z in -r..=r {
+            for y in -r..=r {
+                for x in -r..=r {
+                    let offset = IVec3::new(x, y, z);
+                    if self.shape == BrushShape::Sphere && offset.dot(offset) > r * r {
+                        continue;
+                    }
+                    if utils::in_bounds(center + offset, bounds) {
+                        cells.push(center + offset);
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, states: u8) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.shape, BrushShape::Sphere, "sphere");
+            ui.selectable_value(&mut self.shape, BrushShape::Cube, "cube");
+        });
+        ui.add(egui::Slider::new(&mut self.radius, 0..=8).text("brush radius"));
+        self.state = self.state.clamp(1, states);
+        ui.add(egui::Slider::new(&mut self.state, 1..=states).text("brush state"));
+        if let Some((hit, _)) = self.hovered {
+            ui.label(format!("cursor: {} {} {}", hit.x, hit.y, hit.z));
+        }
+    }
+}
+
+// walks the grid cells along a ray in grid space (Amanatides & Woo), returning
+// the first cell `occupied` accepts together with the cell visited before it.
+pub fn cast(
+    origin: Vec3,
+    direction: Vec3,
+    bounds: i32,
+    mut occupied: impl FnMut(IVec3) -> bool,
+) -> Option<(IVec3, IVec3)> {
+    let size = bounds as f32;
+    let mut t_enter = 0.0f32;
+    let mut t_exit = f32::INFINITY;
+    for axis in 0..3 {
+        if direction[axis].abs() < f32::EPSILON {
+            if origin[axis] < 0.0 || origin[axis] > size {
+                return None;
+            }
+            continue;
+        }
+        let t0 = -origin[axis] / direction[axis];
+        let t1 = (size - origin[axis]) / direction[axis];
+        t_enter = t_enter.max(t0.min(t1));
+        t_exit = t_exit.min(t0.max(t1));
+    }
+    if t_enter > t_exit {
+        return None;
+    }
+
+    let start = origin + direction * t_enter;
+    let mut cell = start
+        .floor()
+        .as_ivec3()
+        .clamp(IVec3::ZERO, IVec3::splat(bounds - 1));
+    // `signum` would turn 0.0 into 1.0.
+    let step = IVec3::select(direction.cmpgt(Vec3::ZERO), IVec3::ONE, IVec3::ZERO)
+        - IVec3::select(direction.cmplt(Vec3::ZERO), IVec3::ONE, IVec3::ZERO);
+    let mut t_max = Vec3::ZERO;
+    let mut t_delta = Vec3::ZERO;
+    for axis in 0..3 {
+        if step[axis] == 0 {
+            t_max[axis] = f32::INFINITY;
+            t_delta[axis] = f32::INFINITY;
+            continue;
+        }
+        let boundary = (cell[axis] + step[axis].max(0)) as f32;
+        t_max[axis] = (boundary - start[axis]) / direction[axis];
+        t_delta[axis] = 1.0 / direction[axis].abs();
+    }
+
+    let mut previous = cell;
+    while utils::in_bounds(cell, bounds) {
+        if occupied(cell) {
+            return Some((cell, previous));
+        }
+        previous = cell;
+        let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+        cell[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+    }
+    None
+}
+
+fn cursor_ray(
+    windows: &Windows,
+    camera: &Camera,
+    transform: &GlobalTransform,
+) -> Option<(Vec3, Vec3)> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    let size = Vec2::new(window.width(), window.height());
+    let ndc = cursor / size * 2.0 - Vec2::ONE;
+
+    // bevy uses a reversed, infinite depth range: 1 is the near plane.
+    let ndc_to_world = transform.compute_matrix() * camera.projection_matrix().inverse();
+    let near = ndc_to_world.project_point3(ndc.extend(1.0));
+    let far = ndc_to_world.project_point3(ndc.extend(0.5));
+    Some((near, (far - near).normalize()))
+}
+
+pub fn paint(
+    mut sims: ResMut<Sims>,
+    windows: Res<Windows>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_ctx: ResMut<EguiContext>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    if egui_ctx.ctx_mut().wants_pointer_input() || egui_ctx.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    let ray = cameras
+        .iter()
+        .find_map(|(camera, transform)| cursor_ray(&windows, camera, transform));
+    let (origin, direction) = match ray {
+        Some(ray) => ray,
+        None => return,
+    };
+
+    // plain clicks and drags belong to the camera.
+    let ctrl = keys.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    sims.brush_stroke(
+        origin,
+        direction,
+        ctrl && mouse.just_pressed(MouseButton::Left),
+        ctrl && mouse.just_pressed(MouseButton::Right),
+    );
+}
+
+pub struct BrushPlugin;
+impl Plugin for BrushPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(paint);
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use bevy::{
     core_pipeline::core_3d::Transparent3d,
     ecs::{
@@ -16,31 +18,135 @@ use bevy::{
             SetItemPipeline, TrackedRenderPass,
         },
         render_resource::*,
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         view::ExtractedView,
         RenderApp, RenderStage,
     },
+    utils::HashMap,
 };
 use bytemuck::{Pod, Zeroable};
 
 use crate::utils;
 
+// one instance per drawn cell. a cell keeps its slot for as long as it is
+// drawn and the last instance moves into the hole it leaves, so a generation
+// only touches the slots of the cells that changed.
+#[derive(Component, Default)]
+pub struct InstanceMaterialData {
+    instances: Vec<InstanceData>,
+    // slot -> cell index and back, `NO_SLOT` for cells that aren't drawn.
+    cells: Vec<usize>,
+    slots: Vec<u32>,
+    // slots written since the last frame was extracted.
+    dirty: Vec<u32>,
+}
+
+const NO_SLOT: u32 = u32::MAX;
+// unchanged instances between two dirty ranges closer than this are uploaded
+// again rather than issuing another write.
+const MERGE_GAP: usize = 16;
+
+impl InstanceMaterialData {
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    // forgets every instance, the next frame uploads the whole buffer again.
+    pub fn reset(&mut self, cell_count: usize) {
+        self.instances.clear();
+        self.cells.clear();
+        self.slots.clear();
+        self.slots.resize(cell_count, NO_SLOT);
+        self.dirty.clear();
+    }
+
+    // called once per frame before any `set`, the previous frame's changes
+    // have been extracted by now.
+    pub fn begin_frame(&mut self) {
+        self.dirty.clear();
+    }
+
+    pub fn set(&mut self, cell: usize, instance: Option<InstanceData>) {
+        let slot = self.slots[cell];
+        match instance {
+            Some(instance) if slot == NO_SLOT => {
+                self.slots[cell] = self.instances.len() as u32;
+                self.dirty.push(self.instances.len() as u32);
+                self.instances.push(instance);
+                self.cells.push(cell);
+            }
+            Some(instance) if self.instances[slot as usize] != instance => {
+                self.instances[slot as usize] = instance;
+                self.dirty.push(slot);
+            }
+            None if slot != NO_SLOT => {
+                let last = self.cells.len() - 1;
+                self.instances.swap_remove(slot as usize);
+                self.cells.swap_remove(slot as usize);
+                self.slots[cell] = NO_SLOT;
+                if (slot as usize) < last {
+                    self.slots[self.cells[slot as usize]] = slot;
+                    self.dirty.push(slot);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn dirty_ranges(&self) -> Vec<Range<usize>> {
+        let mut dirty: Vec<usize> = self
+            .dirty
+            .iter()
+            .map(|slot| *slot as usize)
+            .filter(|slot| *slot < self.instances.len())
+            .collect();
+        dirty.sort_unstable();
+
+        let mut ranges: Vec<Range<usize>> = vec![];
+        for slot in dirty {
+            match ranges.last_mut() {
+                Some(range) if slot <= range.end + MERGE_GAP => range.end = range.end.max(slot + 1),
+                _ => ranges.push(slot..slot + 1),
+            }
+        }
+        ranges
+    }
+}
+
+// what the render world needs of `InstanceMaterialData` each frame: the
+// instance count and the contents of the slots that changed.
 #[derive(Component)]
-pub struct InstanceMaterialData(pub Vec<InstanceData>);
-impl ExtractComponent for InstanceMaterialData {
+pub struct ExtractedInstances {
+    length: usize,
+    writes: Vec<(usize, Vec<InstanceData>)>,
+}
+
+impl ExtractComponent for ExtractedInstances {
     type Filter = ();
     type Query = &'static InstanceMaterialData;
 
     fn extract_component(item: QueryItem<Self::Query>) -> Self {
-        InstanceMaterialData(item.0.clone())
+        ExtractedInstances {
+            length: item.len(),
+            writes: item
+                .dirty_ranges()
+                .into_iter()
+                .map(|range| (range.start, item.instances[range].to_vec()))
+                .collect(),
+        }
     }
 }
 
 pub struct CellMaterialPlugin;
 impl Plugin for CellMaterialPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(ExtractComponentPlugin::<InstanceMaterialData>::default());
+        app.add_plugin(ExtractComponentPlugin::<ExtractedInstances>::default());
         app.sub_app_mut(RenderApp)
+            .init_resource::<InstanceBuffers>()
             .add_render_command::<Transparent3d, DrawCustom>()
             .init_resource::<CellPipeline>()
             .init_resource::<SpecializedMeshPipelines<CellPipeline>>()
@@ -49,7 +155,7 @@ impl Plugin for CellMaterialPlugin {
     }
 }
 
-#[derive(Clone, Copy, Pod, Zeroable)]
+#[derive(Clone, Copy, PartialEq, Pod, Zeroable)]
 #[repr(C)]
 pub struct InstanceData {
     pub position: Vec3,
@@ -67,7 +173,7 @@ fn queue_custom(
     meshes: Res<RenderAssets<Mesh>>,
     material_meshes: Query<
         (Entity, &MeshUniform, &Handle<Mesh>),
-        (With<Handle<Mesh>>, With<InstanceMaterialData>),
+        (With<Handle<Mesh>>, With<ExtractedInstances>),
     >,
     mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
 ) {
@@ -99,28 +205,82 @@ fn queue_custom(
     }
 }
 
-#[derive(Component)]
 pub struct InstanceBuffer {
     buffer: Buffer,
+    capacity: usize,
     length: usize,
 }
 
+// render world entities are spawned again every frame, so the buffers live
+// in a resource to survive from one frame to the next.
+#[derive(Default)]
+pub struct InstanceBuffers(HashMap<Entity, InstanceBuffer>);
+
+const MIN_CAPACITY: usize = 1024;
+
+fn create_instance_buffer(render_device: &RenderDevice, capacity: usize) -> Buffer {
+    render_device.create_buffer(&BufferDescriptor {
+        label: Some("instance data buffer"),
+        size: (capacity * std::mem::size_of::<InstanceData>()) as u64,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    })
+}
+
 fn prepare_instance_buffers(
-    mut commands: Commands,
-    query: Query<(Entity, &InstanceMaterialData)>,
+    query: Query<(Entity, &ExtractedInstances)>,
+    mut instance_buffers: ResMut<InstanceBuffers>,
     render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
 ) {
-    for (entity, instance_data) in query.iter() {
-        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            label: Some("instance data buffer"),
-            contents: bytemuck::cast_slice(instance_data.0.as_slice()),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
-        commands.entity(entity).insert(InstanceBuffer {
-            buffer,
-            length: instance_data.0.len(),
-        });
+    let instance_size = std::mem::size_of::<InstanceData>();
+    for (entity, instances) in query.iter() {
+        let instance_buffer = instance_buffers
+            .0
+            .entry(entity)
+            .or_insert_with(|| InstanceBuffer {
+                buffer: create_instance_buffer(&render_device, MIN_CAPACITY),
+                capacity: MIN_CAPACITY,
+                length: 0,
+            });
+
+        if instances.length > instance_buffer.capacity {
+            // only the changed slots are sent over, so the instances that are
+            // already on the gpu have to be carried over to the new buffer.
+            let capacity = instances.length.next_power_of_two();
+            let buffer = create_instance_buffer(&render_device, capacity);
+            if instance_buffer.length > 0 {
+                let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("instance buffer growth"),
+                });
+                encoder.copy_buffer_to_buffer(
+                    &instance_buffer.buffer,
+                    0,
+                    &buffer,
+                    0,
+                    (instance_buffer.length * instance_size) as u64,
+                );
+                render_queue.submit([encoder.finish()]);
+            }
+            instance_buffer.buffer = buffer;
+            instance_buffer.capacity = capacity;
+        }
+
+        // queued writes run at the start of the next submission, after the
+        // copy above.
+        for (start, data) in instances.writes.iter() {
+            render_queue.write_buffer(
+                &instance_buffer.buffer,
+                (start * instance_size) as u64,
+                bytemuck::cast_slice(data.as_slice()),
+            );
+        }
+        instance_buffer.length = instances.length;
     }
+
+    instance_buffers
+        .0
+        .retain(|entity, _| query.get(*entity).is_ok());
 }
 
 pub struct CellPipeline {
@@ -202,20 +362,21 @@ impl EntityRenderCommand for DrawMeshedInstanced {
     type Param = (
         SRes<RenderAssets<Mesh>>,
         SQuery<Read<Handle<Mesh>>>,
-        SQuery<Read<InstanceBuffer>>,
+        SRes<InstanceBuffers>,
     );
 
     #[inline]
     fn render<'w>(
         _view: Entity,
         item: Entity,
-        (meshes, mesh_query, instance_buffer_query): SystemParamItem<'w, '_, Self::Param>,
+        (meshes, mesh_query, instance_buffers): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
         let mesh_handle = mesh_query.get(item).expect("missing mesh");
-        let instance_buffer = instance_buffer_query
-            .get(item)
-            .expect("missing instance buffer");
+        let instance_buffer = match instance_buffers.into_inner().0.get(&item) {
+            Some(instance_buffer) => instance_buffer,
+            None => return RenderCommandResult::Failure,
+        };
 
         let gpu_mesh = match meshes.into_inner().get(mesh_handle) {
             Some(gpu_mesh) => gpu_mesh,
@@ -247,6 +408,64 @@ pub struct CellRenderer {
     pub bounds: i32,
     pub values: Vec<u8>,
     pub neighbors: Vec<u8>,
+    population: Moments,
+    // cells whose value or neighbor count changed since `take_changes`.
+    changes: Vec<usize>,
+    changed: Vec<bool>,
+}
+
+// running sums over the non-dead cells, kept up to date by `set` so any
+// simulator's render produces them for free.
+#[derive(Clone, Default)]
+struct Moments {
+    count: i64,
+    sum: [i64; 3],
+    // live cells in every plane along each axis, so the bounding box survives
+    // cells dying at its edges.
+    planes: [Vec<u32>; 3],
+}
+
+impl Moments {
+    fn new(bounds: i32) -> Self {
+        let planes = vec![0; bounds.max(0) as usize];
+        Self {
+            count: 0,
+            sum: [0; 3],
+            planes: [planes.clone(), planes.clone(), planes],
+        }
+    }
+
+    fn add(&mut self, pos: IVec3, sign: i64) {
+        self.count += sign;
+        for axis in 0..3 {
+            let coord = pos[axis];
+            self.sum[axis] += sign * coord as i64;
+            let plane = &mut self.planes[axis][coord as usize];
+            *plane = (*plane as i64 + sign) as u32;
+        }
+    }
+
+    fn bounding_box(&self) -> Option<(IVec3, IVec3)> {
+        let mut min = IVec3::ZERO;
+        let mut max = IVec3::ZERO;
+        for axis in 0..3 {
+            let planes = &self.planes[axis];
+            min[axis] = planes.iter().position(|count| *count > 0)? as i32;
+            max[axis] = planes.iter().rposition(|count| *count > 0)? as i32;
+        }
+        Some((min, max))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Population {
+    pub count: usize,
+    // in grid coordinates.
+    pub center_of_mass: Vec3,
+    // a sphere around the center of mass holding every live cell whole. it
+    // reaches the farthest corner of their bounding box, so it can be a bit
+    // larger than needed but never leaves a cell out.
+    pub radius: f32,
 }
 
 impl CellRenderer {
@@ -255,14 +474,59 @@ impl CellRenderer {
             bounds: 0,
             values: vec![],
             neighbors: vec![],
+            population: Moments::default(),
+            changes: vec![],
+            changed: vec![],
         }
     }
 
     pub fn clear(&mut self) {
-        self.values.truncate(0);
-        self.values.resize(self.cell_count(), 0);
-        self.neighbors.truncate(0);
-        self.neighbors.resize(self.cell_count(), 0);
+        for idx in 0..self.cell_count() {
+            if self.values[idx] != 0 || self.neighbors[idx] != 0 {
+                self.mark_changed(idx);
+            }
+        }
+        self.values.fill(0);
+        self.neighbors.fill(0);
+        self.population = Moments::new(self.bounds);
+    }
+
+    fn mark_changed(&mut self, idx: usize) {
+        if !self.changed[idx] {
+            self.changed[idx] = true;
+            self.changes.push(idx);
+        }
+    }
+
+    // every cell that changed since the last call, in no particular order,
+    // as reported by the sim's `render_changes`.
+    pub fn take_changes(&mut self) -> Vec<usize> {
+        for idx in self.changes.iter() {
+            self.changed[*idx] = false;
+        }
+        std::mem::take(&mut self.changes)
+    }
+
+    pub fn population(&self) -> Population {
+        let moments = &self.population;
+        let count = moments.count.max(1) as f64;
+        let mean = [0, 1, 2].map(|axis| (moments.sum[axis] as f64 / count) as f32);
+        let center_of_mass = Vec3::from(mean);
+        let radius = match moments.bounding_box() {
+            Some((min, max)) => {
+                // cells are unit cubes around their position.
+                let corner = (center_of_mass - min.as_vec3())
+                    .max(max.as_vec3() - center_of_mass)
+                    + Vec3::splat(0.5);
+                corner.length()
+            }
+            None => 0.0,
+        };
+        Population {
+            count: moments.count as usize,
+            center_of_mass,
+            radius,
+        }
     }
 
     pub fn cell_count(&self) -> usize {
@@ -271,14 +535,25 @@ impl CellRenderer {
 
     pub fn set_bounds(&mut self, new_bounds: i32) {
         if new_bounds != self.bounds {
-            let new_count = new_bounds.pow(3);
-            self.values.resize(new_count as usize, 0);
-            self.neighbors.resize(new_count as usize, 0);
+            let new_count = new_bounds.pow(3) as usize;
+            self.values = vec![0; new_count];
+            self.neighbors = vec![0; new_count];
+            self.changes.clear();
+            self.changed = vec![false; new_count];
+            self.population = Moments::new(new_bounds);
             self.bounds = new_bounds;
         }
     }
 
     pub fn set(&mut self, idx: usize, value: u8, neighbors: u8) {
+        let old = self.values[idx];
+        if (old == 0) != (value == 0) {
+            let pos = utils::idx_to_pos(idx, self.bounds);
+            self.population.add(pos, if value == 0 { -1 } else { 1 });
+        }
+        if old != value || self.neighbors[idx] != neighbors {
+            self.mark_changed(idx);
+        }
         self.values[idx] = value;
         self.neighbors[idx] = neighbors;
     }
@@ -0,0 +1,321 @@
+use bevy::{math::ivec3, prelude::*, tasks::TaskPool};
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{Sim, SimCapabilities},
+    rule::Rule,
+    utils,
+};
+
+// one bit per cell along x, 64 cells to a word. live cells and dying cells
+// (the single decay step of a 2 state rule) are kept in separate bitplanes.
+pub struct CellsBitPacked {
+    alive: Vec<u64>,
+    dying: Vec<u64>,
+    words_per_row: usize,
+    bounding_size: i32,
+    states: u8,
+    // the bitplanes as last handed to the renderer, empty when it has to be
+    // redrawn.
+    drawn_alive: Vec<u64>,
+    drawn_dying: Vec<u64>,
+    drawn_states: u8,
+}
+
+enum Cell {
+    Dead,
+    Dying,
+    Alive,
+}
+
+// bit-sliced counter, enough for the 26 moore neighbors.
+#[derive(Clone, Copy, Default)]
+struct Counter([u64; 5]);
+
+impl Counter {
+    fn add(&mut self, bits: u64) {
+        let mut carry = bits;
+        for plane in self.0.iter_mut() {
+            let sum = *plane ^ carry;
+            carry &= *plane;
+            *plane = sum;
+        }
+    }
+
+    fn equals(&self, count: u8) -> u64 {
+        self.0.iter().enumerate().fold(!0, |mask, (bit, plane)| {
+            mask & if count >> bit & 1 == 1 {
+                *plane
+            } else {
+                !plane
+            }
+        })
+    }
+
+    fn matches(&self, value: &crate::rule::Value, max: u8) -> u64 {
+        (0..=max)
+            .filter(|count| value.in_range(*count))
+            .fold(0, |mask, count| mask | self.equals(count))
+    }
+}
+
+impl CellsBitPacked {
+    pub fn new() -> Self {
+        Self {
+            alive: vec![],
+            dying: vec![],
+            words_per_row: 0,
+            bounding_size: 0,
+            states: 2,
+            drawn_alive: vec![],
+            drawn_dying: vec![],
+            drawn_states: 0,
+        }
+    }
+
+    fn row(&self, y: i32, z: i32) -> usize {
+        (y + z * self.bounding_size) as usize * self.words_per_row
+    }
+
+    fn last_word_mask(&self) -> u64 {
+        match self.bounding_size % 64 {
+            0 => !0,
+            bits => (1 << bits) - 1,
+        }
+    }
+
+    // every row rotated by one cell along x, so bit x holds cell x + dx.
+    fn shifted(&self, dx: i32) -> Vec<u64> {
+        let words = self.words_per_row;
+        let bounds = self.bounding_size as usize;
+        let last_mask = self.last_word_mask();
+        let mut shifted = vec![0; self.alive.len()];
+
+        for (row, out) in self.alive.chunks(words).zip(shifted.chunks_mut(words)) {
+            let first_bit = row[0] & 1;
+            let last_bit = row[(bounds - 1) / 64] >> ((bounds - 1) % 64) & 1;
+            for w in 0..words {
+                out[w] = if dx > 0 {
+                    let carry = if w + 1 < words { row[w + 1] << 63 } else { 0 };
+                    row[w] >> 1 | carry
+                } else {
+                    let carry = if w > 0 { row[w - 1] >> 63 } else { 0 };
+                    row[w] << 1 | carry
+                };
+            }
+
+            if dx > 0 {
+                out[(bounds - 1) / 64] |= first_bit << ((bounds - 1) % 64);
+            } else {
+                out[0] |= last_bit;
+                out[words - 1] &= last_mask;
+            }
+        }
+        shifted
+    }
+
+    pub fn tick(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        let bounds = self.bounding_size;
+        let words = self.words_per_row;
+        let layer = bounds as usize * words;
+        let last_mask = self.last_word_mask();
+        let decays = rule.states == 2;
+        let max_neighbors = rule.neighbor_method.get_neighbor_iter().len() as u8;
+
+        let west = self.shifted(-1);
+        let east = self.shifted(1);
+        let this = &*self;
+        let (west, east) = (&west, &east);
+
+        let mut next_alive = vec![0; self.alive.len()];
+        let mut next_dying = vec![0; self.dying.len()];
+
+        task_pool.scope(|s| {
+            for (z, (alive_layer, dying_layer)) in next_alive
+                .chunks_mut(layer)
+                .zip(next_dying.chunks_mut(layer))
+                .enumerate()
+            {
+                s.spawn(async move {
+                    let z = z as i32;
+                    for y in 0..bounds {
+                        let out = y as usize * words;
+                        let here = this.row(y, z);
+                        for w in 0..words {
+                            let mut counter = Counter::default();
+                            for offset in rule.neighbor_method.get_neighbor_iter() {
+                                let src = utils::wrap(ivec3(0, y + offset.y, z + offset.z), bounds);
+                                let grid = match offset.x {
+                                    -1 => west,
+                                    1 => east,
+                                    _ => &this.alive,
+                                };
+                                counter.add(grid[this.row(src.y, src.z) + w]);
+                            }
+
+                            let alive = this.alive[here + w];
+                            let dying = this.dying[here + w];
+                            let survive =
+                                alive & counter.matches(&rule.survival_rule, max_neighbors);
+                            let born =
+                                !alive & !dying & counter.matches(&rule.birth_rule, max_neighbors);
+
+                            let mask = if w + 1 == words { last_mask } else { !0 };
+                            alive_layer[out + w] = (survive | born) & mask;
+                            dying_layer[out + w] = if decays { alive & !survive & mask } else { 0 };
+                        }
+                    }
+                });
+            }
+        });
+
+        self.alive = next_alive;
+        self.dying = next_dying;
+    }
+
+    fn set(&mut self, x: i32, y: i32, z: i32, cell: Cell) {
+        let word = self.row(y, z) + x as usize / 64;
+        let bit = 1 << (x % 64);
+        self.alive[word] &= !bit;
+        self.dying[word] &= !bit;
+        match cell {
+            Cell::Alive => self.alive[word] |= bit,
+            Cell::Dying => self.dying[word] |= bit,
+            Cell::Dead => {}
+        }
+    }
+}
+
+impl Sim for CellsBitPacked {
+    fn update(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        self.states = rule.states;
+        self.tick(rule, task_pool);
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        renderer.clear();
+        let bounds = self.bounding_size;
+        for z in 0..bounds {
+            for y in 0..bounds {
+                let row = self.row(y, z);
+                for x in 0..bounds {
+                    let word = row + x as usize / 64;
+                    let bit = 1 << (x % 64);
+                    let value = if self.alive[word] & bit != 0 {
+                        self.states
+                    } else if self.dying[word] & bit != 0 {
+                        1
+                    } else {
+                        continue;
+                    };
+                    renderer.set_pos(ivec3(x, y, z), value, 0);
+                }
+            }
+        }
+    }
+
+    // only the bits that differ from the drawn bitplanes reach the renderer.
+    fn render_changes(&mut self, renderer: &mut CellRenderer, full: bool) {
+        if full || self.drawn_alive.len() != self.alive.len() || self.drawn_states != self.states {
+            self.render(renderer);
+        } else {
+            let bounds = self.bounding_size;
+            for word in 0..self.alive.len() {
+                let alive = self.alive[word];
+                let dying = self.dying[word];
+                let mut changed = (alive ^ self.drawn_alive[word]) | (dying ^ self.drawn_dying[word]);
+                let row = (word / self.words_per_row) as i32;
+                while changed != 0 {
+                    let bit = changed.trailing_zeros();
+                    changed &= changed - 1;
+                    let x = (word % self.words_per_row * 64) as i32 + bit as i32;
+                    if x >= bounds {
+                        continue;
+                    }
+                    let value = if alive >> bit & 1 != 0 {
+                        self.states
+                    } else if dying >> bit & 1 != 0 {
+                        1
+                    } else {
+                        0
+                    };
+                    renderer.set_pos(ivec3(x, row % bounds, row / bounds), value, 0);
+                }
+            }
+        }
+        self.drawn_alive.clone_from(&self.alive);
+        self.drawn_dying.clone_from(&self.dying);
+        self.drawn_states = self.states;
+    }
+
+    fn capabilities(&self) -> SimCapabilities {
+        SimCapabilities {
+            max_states: 2,
+            multi_threaded: true,
+            ..Default::default()
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        self.states = rule.states;
+        let bounds = self.bounding_size;
+        let mut cells = vec![];
+        utils::make_some_noise_default(utils::center(bounds), |pos| {
+            if utils::in_bounds(pos, bounds) {
+                cells.push(pos);
+            }
+        });
+        for pos in cells {
+            self.set(pos.x, pos.y, pos.z, Cell::Alive);
+        }
+    }
+
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: u8) {
+        self.states = rule.states;
+        let cell = if value == 0 {
+            Cell::Dead
+        } else if value == rule.states {
+            Cell::Alive
+        } else {
+            Cell::Dying
+        };
+        self.set(pos.x, pos.y, pos.z, cell);
+    }
+
+    fn get_cell(&self, pos: IVec3) -> u8 {
+        let word = self.row(pos.y, pos.z) + pos.x as usize / 64;
+        let bit = 1 << (pos.x % 64);
+        if self.alive[word] & bit != 0 {
+            self.states
+        } else if self.dying[word] & bit != 0 {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn cell_count(&self) -> usize {
+        self.alive
+            .iter()
+            .chain(self.dying.iter())
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.bounding_size
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.bounding_size {
+            self.words_per_row = (new_bounds as usize).div_ceil(64);
+            let count = new_bounds.pow(2) as usize * self.words_per_row;
+            self.alive = vec![0; count];
+            self.dying = vec![0; count];
+            self.drawn_alive.clear();
+            self.drawn_dying.clear();
+        }
+        self.bounding_size = new_bounds;
+        new_bounds
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::{math::ivec3, prelude::*, tasks::TaskPool};
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{Sim, SimCapabilities},
+    rule::Rule,
+    utils,
+};
+
+const CHUNK_SIZE: i32 = 16;
+
+// dense grid split into chunks; only chunks that changed last generation,
+// or that border one that did, get recomputed.
+pub struct CellsChunked {
+    values: Vec<u8>,
+    active: Vec<bool>,
+    chunks_per_axis: i32,
+    active_chunks: usize,
+    bounding_size: i32,
+    // cells set since the last `render_changes`, all of them while `redraw`.
+    touched: Vec<usize>,
+    redraw: bool,
+}
+
+impl CellsChunked {
+    pub fn new() -> Self {
+        Self {
+            values: vec![],
+            active: vec![],
+            chunks_per_axis: 0,
+            active_chunks: 0,
+            bounding_size: 0,
+            touched: vec![],
+            redraw: true,
+        }
+    }
+
+    fn chunk_count(&self) -> usize {
+        self.chunks_per_axis.pow(3) as usize
+    }
+
+    fn wake_all(&mut self) {
+        self.active.iter_mut().for_each(|active| *active = true);
+    }
+
+    pub fn tick(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        let bounds = self.bounding_size;
+        let chunks_per_axis = self.chunks_per_axis;
+        let values = &self.values;
+        let neighbor_iter = rule.neighbor_method.get_neighbor_iter();
+
+        let active: Vec<usize> = (0..self.chunk_count())
+            .filter(|chunk| self.active[*chunk])
+            .collect();
+        self.active_chunks = active.len();
+
+        let changes = task_pool.scope(|s| {
+            for chunk in active.iter() {
+                s.spawn(async move {
+                    let min = utils::idx_to_pos(*chunk, chunks_per_axis) * CHUNK_SIZE;
+                    let max = (min + CHUNK_SIZE).min(IVec3::splat(bounds));
+
+                    let mut changes = vec![];
+                    for z in min.z..max.z {
+                        for y in min.y..max.y {
+                            for x in min.x..max.x {
+                                let pos = ivec3(x, y, z);
+                                let idx = utils::pos_to_idx(pos, bounds);
+                                let neighbors = neighbor_iter
+                                    .iter()
+                                    .filter(|offset| {
+                                        let neighbor = utils::wrap(pos + **offset, bounds);
+                                        values[utils::pos_to_idx(neighbor, bounds)] == rule.states
+                                    })
+                                    .count() as u8;
+
+                                let value = rule.next_state(values[idx], neighbors);
+                                if value != values[idx] {
+                                    changes.push((idx, value));
+                                }
+                            }
+                        }
+                    }
+                    changes
+                });
+            }
+        });
+
+        self.active.iter_mut().for_each(|active| *active = false);
+        for (idx, value) in changes.into_iter().flatten() {
+            self.values[idx] = value;
+            self.touched.push(idx);
+            self.wake(utils::idx_to_pos(idx, bounds));
+        }
+    }
+
+    // marks the chunk holding `pos` and all chunks around it for recomputation.
+    fn wake(&mut self, pos: IVec3) {
+        let chunk = pos / CHUNK_SIZE;
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let neighbor = utils::wrap(chunk + ivec3(x, y, z), self.chunks_per_axis);
+                    self.active[utils::pos_to_idx(neighbor, self.chunks_per_axis)] = true;
+                }
+            }
+        }
+    }
+}
+
+impl Sim for CellsChunked {
+    fn update(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        self.tick(rule, task_pool);
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        for (idx, value) in self.values.iter().enumerate() {
+            renderer.set(idx, *value, 0);
+        }
+    }
+
+    fn render_changes(&mut self, renderer: &mut CellRenderer, full: bool) {
+        if full || self.redraw {
+            self.render(renderer);
+        } else {
+            for idx in self.touched.iter() {
+                renderer.set(*idx, self.values[*idx], 0);
+            }
+        }
+        self.touched.clear();
+        self.redraw = false;
+    }
+
+    fn stats(&self) -> Option<String> {
+        Some(format!(
+            "active chunks: {} / {}",
+            self.active_chunks,
+            self.chunk_count()
+        ))
+    }
+
+    fn capabilities(&self) -> SimCapabilities {
+        SimCapabilities {
+            multi_threaded: true,
+            ..Default::default()
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        let bounds = self.bounding_size;
+        let values = &mut self.values;
+        let touched = &mut self.touched;
+        utils::make_some_noise_default(utils::center(bounds), |pos| {
+            if utils::in_bounds(pos, bounds) {
+                let idx = utils::pos_to_idx(pos, bounds);
+                values[idx] = rule.states;
+                touched.push(idx);
+            }
+        });
+        self.wake_all();
+    }
+
+    fn set_cell(&mut self, _rule: &Rule, pos: IVec3, value: u8) {
+        let idx = utils::pos_to_idx(pos, self.bounding_size);
+        self.values[idx] = value;
+        self.touched.push(idx);
+        self.wake(pos);
+    }
+
+    fn get_cell(&self, pos: IVec3) -> u8 {
+        self.values[utils::pos_to_idx(pos, self.bounding_size)]
+    }
+
+    fn cell_count(&self) -> usize {
+        self.values.iter().filter(|value| **value != 0).count()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.bounding_size
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.bounding_size {
+            self.values = vec![0; new_bounds.pow(3) as usize];
+            self.chunks_per_axis = (new_bounds + CHUNK_SIZE - 1) / CHUNK_SIZE;
+            self.active = vec![true; self.chunk_count()];
+            self.touched.clear();
+            self.redraw = true;
+        }
+        self.bounding_size = new_bounds;
+        new_bounds
+    }
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, tasks::TaskPool};
+use bevy_egui::egui;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{Sim, SimCapabilities},
+    rule::Rule,
+    utils,
+};
+
+// past this many nodes the tables are rebuilt from the live tree.
+const MAX_NODES: usize = 1 << 22;
+const MAX_STEP_EXPONENT: u8 = 16;
+
+type NodeId = u32;
+
+// children are indexed by octant: x + 2 * y + 4 * z, each bit selecting the upper half.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum NodeKey {
+    Leaf(u8),
+    Branch([NodeId; 8]),
+}
+
+struct Node {
+    key: NodeKey,
+    level: u8,
+    population: u64,
+}
+
+pub struct CellsHashlife {
+    nodes: Vec<Node>,
+    lookup: HashMap<NodeKey, NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+    root: NodeId,
+    origin: IVec3,
+    rule: Option<Rule>,
+    step_exponent: u8,
+    generation: u64,
+    bounding_size: i32,
+    // root and origin as last handed to the renderer.
+    drawn: Option<(NodeId, IVec3)>,
+}
+
+fn octant(x: usize, y: usize, z: usize) -> usize {
+    x + 2 * y + 4 * z
+}
+
+impl CellsHashlife {
+    pub fn new() -> Self {
+        let mut this = Self {
+            nodes: vec![],
+            lookup: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![],
+            root: 0,
+            origin: IVec3::ZERO,
+            rule: None,
+            step_exponent: 0,
+            generation: 0,
+            bounding_size: 0,
+            drawn: None,
+        };
+        this.root = this.empty(1);
+        this
+    }
+
+    fn node(&mut self, key: NodeKey) -> NodeId {
+        if let Some(id) = self.lookup.get(&key) {
+            return *id;
+        }
+
+        let (level, population) = match key {
+            NodeKey::Leaf(value) => (0, (value != 0) as u64),
+            NodeKey::Branch(children) => (
+                self.nodes[children[0] as usize].level + 1,
+                children
+                    .iter()
+                    .map(|child| self.nodes[*child as usize].population)
+                    .sum(),
+            ),
+        };
+
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            key,
+            level,
+            population,
+        });
+        self.lookup.insert(key, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let node = match self.empty.last() {
+                None => self.node(NodeKey::Leaf(0)),
+                Some(child) => self.node(NodeKey::Branch([*child; 8])),
+            };
+            self.empty.push(node);
+        }
+        self.empty[level as usize]
+    }
+
+    fn children(&self, id: NodeId) -> [NodeId; 8] {
+        match self.nodes[id as usize].key {
+            NodeKey::Branch(children) => children,
+            NodeKey::Leaf(_) => panic!("leaf nodes have no children"),
+        }
+    }
+
+    fn value(&self, id: NodeId) -> u8 {
+        match self.nodes[id as usize].key {
+            NodeKey::Leaf(value) => value,
+            NodeKey::Branch(_) => panic!("branch nodes have no value"),
+        }
+    }
+
+    fn level(&self, id: NodeId) -> u8 {
+        self.nodes[id as usize].level
+    }
+
+    fn size(level: u8) -> i32 {
+        1 << level
+    }
+
+    fn set_cell(&mut self, id: NodeId, pos: IVec3, value: u8) -> NodeId {
+        let level = self.level(id);
+        if level == 0 {
+            return self.node(NodeKey::Leaf(value));
+        }
+
+        let half = Self::size(level - 1);
+        let upper = pos.cmpge(IVec3::splat(half));
+        let child = octant(upper.x as usize, upper.y as usize, upper.z as usize);
+        let offset = IVec3::select(upper, IVec3::splat(half), IVec3::ZERO);
+
+        let mut children = self.children(id);
+        children[child] = self.set_cell(children[child], pos - offset, value);
+        self.node(NodeKey::Branch(children))
+    }
+
+    fn cell(&self, id: NodeId, pos: IVec3) -> u8 {
+        let node = &self.nodes[id as usize];
+        match node.key {
+            _ if node.population == 0 => 0,
+            NodeKey::Leaf(value) => value,
+            NodeKey::Branch(children) => {
+                let half = Self::size(node.level - 1);
+                let upper = pos.cmpge(IVec3::splat(half));
+                let child = octant(upper.x as usize, upper.y as usize, upper.z as usize);
+                let offset = IVec3::select(upper, IVec3::splat(half), IVec3::ZERO);
+                self.cell(children[child], pos - offset)
+            }
+        }
+    }
+
+    fn set_world_cell(&mut self, pos: IVec3, value: u8) {
+        loop {
+            let size = Self::size(self.level(self.root));
+            let local = pos - self.origin;
+            if local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(size)).all() {
+                self.root = self.set_cell(self.root, local, value);
+                return;
+            }
+            self.expand();
+        }
+    }
+
+    // wraps the root in empty space, keeping it centered.
+    fn expand(&mut self) {
+        let level = self.level(self.root);
+        let empty = self.empty(level - 1);
+        let old = self.children(self.root);
+
+        let mut children = [0; 8];
+        for (i, child) in children.iter_mut().enumerate() {
+            let mut grandchildren = [empty; 8];
+            grandchildren[7 - i] = old[i];
+            *child = self.node(NodeKey::Branch(grandchildren));
+        }
+
+        self.root = self.node(NodeKey::Branch(children));
+        self.origin -= IVec3::splat(Self::size(level - 1));
+    }
+
+    fn centered(&mut self, id: NodeId) -> NodeId {
+        let children = self.children(id);
+        let mut center = [0; 8];
+        for (i, child) in center.iter_mut().enumerate() {
+            *child = self.children(children[i])[7 - i];
+        }
+        self.node(NodeKey::Branch(center))
+    }
+
+    // the 4x4x4 grid of grandchildren, indexed [x][y][z].
+    fn grandchildren(&self, id: NodeId) -> [[[NodeId; 4]; 4]; 4] {
+        let mut grid = [[[0; 4]; 4]; 4];
+        let children = self.children(id);
+        for (x, plane) in grid.iter_mut().enumerate() {
+            for (y, row) in plane.iter_mut().enumerate() {
+                for (z, cell) in row.iter_mut().enumerate() {
+                    let child = children[octant(x / 2, y / 2, z / 2)];
+                    *cell = self.children(child)[octant(x % 2, y % 2, z % 2)];
+                }
+            }
+        }
+        grid
+    }
+
+    fn combine<const N: usize>(
+        &mut self,
+        grid: &[[[NodeId; N]; N]; N],
+        x: usize,
+        y: usize,
+        z: usize,
+    ) -> NodeId {
+        let mut children = [0; 8];
+        for (i, child) in children.iter_mut().enumerate() {
+            *child = grid[x + (i & 1)][y + ((i >> 1) & 1)][z + ((i >> 2) & 1)];
+        }
+        self.node(NodeKey::Branch(children))
+    }
+
+    // the center half of a level k node, advanced 2^step generations (step <= k - 2).
+    fn step(&mut self, id: NodeId, step: u8) -> NodeId {
+        let level = self.level(id);
+        if self.nodes[id as usize].population == 0 {
+            return self.empty(level - 1);
+        }
+        if let Some(result) = self.results.get(&(id, step)) {
+            return *result;
+        }
+
+        let result = if level == 2 {
+            self.step_leaves(id)
+        } else {
+            let grid = self.grandchildren(id);
+
+            let mut partial = [[[0; 3]; 3]; 3];
+            for (x, plane) in partial.iter_mut().enumerate() {
+                for (y, row) in plane.iter_mut().enumerate() {
+                    for (z, cell) in row.iter_mut().enumerate() {
+                        let sub = self.combine(&grid, x, y, z);
+                        *cell = if step == level - 2 {
+                            self.step(sub, level - 3)
+                        } else {
+                            self.centered(sub)
+                        };
+                    }
+                }
+            }
+
+            let mut children = [0; 8];
+            for (i, child) in children.iter_mut().enumerate() {
+                let sub = self.combine(&partial, i & 1, (i >> 1) & 1, (i >> 2) & 1);
+                *child = self.step(sub, step.min(level - 3));
+            }
+            self.node(NodeKey::Branch(children))
+        };
+
+        self.results.insert((id, step), result);
+        result
+    }
+
+    fn step_leaves(&mut self, id: NodeId) -> NodeId {
+        let grid = self.grandchildren(id);
+        let rule = self.rule.as_ref().expect("stepping without a rule");
+
+        let mut values = [[[0; 4]; 4]; 4];
+        for x in 0..4 {
+            for y in 0..4 {
+                for z in 0..4 {
+                    values[x][y][z] = self.value(grid[x][y][z]);
+                }
+            }
+        }
+
+        let mut next = [0; 8];
+        for (i, cell) in next.iter_mut().enumerate() {
+            let pos = IVec3::new(
+                1 + (i & 1) as i32,
+                1 + ((i >> 1) & 1) as i32,
+                1 + ((i >> 2) & 1) as i32,
+            );
+            let neighbors = rule
+                .neighbor_method
+                .get_neighbor_iter()
+                .iter()
+                .filter(|offset| {
+                    let n = pos + **offset;
+                    values[n.x as usize][n.y as usize][n.z as usize] == rule.states
+                })
+                .count() as u8;
+            *cell = rule.next_state(
+                values[pos.x as usize][pos.y as usize][pos.z as usize],
+                neighbors,
+            );
+        }
+
+        let mut children = [0; 8];
+        for (child, value) in children.iter_mut().zip(next) {
+            *child = self.node(NodeKey::Leaf(value));
+        }
+        self.node(NodeKey::Branch(children))
+    }
+
+    fn is_padded(&mut self) -> bool {
+        let center = self.centered(self.root);
+        let inner = self.centered(center);
+        self.nodes[inner as usize].population == self.nodes[self.root as usize].population
+    }
+
+    pub fn tick(&mut self) {
+        let step = self.step_exponent;
+        while self.level(self.root) < step + 3 || !self.is_padded() {
+            self.expand();
+        }
+
+        let size = Self::size(self.level(self.root));
+        self.root = self.step(self.root, step);
+        self.origin += IVec3::splat(size / 4);
+        self.generation += 1 << step;
+
+        if self.nodes.len() > MAX_NODES {
+            self.collect_garbage();
+        }
+    }
+
+    // rebuilds the node table with only the nodes reachable from the root.
+    fn collect_garbage(&mut self) {
+        let old_nodes = std::mem::take(&mut self.nodes);
+        self.lookup.clear();
+        self.results.clear();
+        self.empty.clear();
+
+        let mut copied = HashMap::new();
+        self.root = self.copy_node(&old_nodes, &mut copied, self.root);
+        // the drawn root's id means nothing in the new table.
+        self.drawn = None;
+    }
+
+    fn copy_node(
+        &mut self,
+        old_nodes: &[Node],
+        copied: &mut HashMap<NodeId, NodeId>,
+        id: NodeId,
+    ) -> NodeId {
+        if let Some(new_id) = copied.get(&id) {
+            return *new_id;
+        }
+
+        let key = match old_nodes[id as usize].key {
+            NodeKey::Leaf(value) => NodeKey::Leaf(value),
+            NodeKey::Branch(children) => {
+                let mut new_children = [0; 8];
+                for (new_child, child) in new_children.iter_mut().zip(children) {
+                    *new_child = self.copy_node(old_nodes, copied, child);
+                }
+                NodeKey::Branch(new_children)
+            }
+        };
+
+        let new_id = self.node(key);
+        copied.insert(id, new_id);
+        new_id
+    }
+
+    fn render_node(&self, renderer: &mut CellRenderer, id: NodeId, origin: IVec3) {
+        let node = &self.nodes[id as usize];
+        if node.population == 0 {
+            return;
+        }
+
+        let size = Self::size(node.level);
+        let bounds = self.bounding_size;
+        if (origin + size).cmple(IVec3::ZERO).any() || origin.cmpge(IVec3::splat(bounds)).any() {
+            return;
+        }
+
+        match node.key {
+            NodeKey::Leaf(value) => renderer.set_pos(origin, value, 0),
+            NodeKey::Branch(children) => {
+                let half = size / 2;
+                for (i, child) in children.iter().enumerate() {
+                    let offset =
+                        IVec3::new((i & 1) as i32, ((i >> 1) & 1) as i32, ((i >> 2) & 1) as i32);
+                    self.render_node(renderer, *child, origin + offset * half);
+                }
+            }
+        }
+    }
+
+    // redraws the cells that differ between two trees of the same level at
+    // the same origin. equal subtrees are the same node, so they are skipped.
+    fn render_diff(&self, renderer: &mut CellRenderer, old: NodeId, new: NodeId, origin: IVec3) {
+        if old == new {
+            return;
+        }
+
+        let level = self.level(new);
+        let size = Self::size(level);
+        let bounds = self.bounding_size;
+        if (origin + size).cmple(IVec3::ZERO).any() || origin.cmpge(IVec3::splat(bounds)).any() {
+            return;
+        }
+
+        match (self.nodes[old as usize].key, self.nodes[new as usize].key) {
+            (_, NodeKey::Leaf(value)) => renderer.set_pos(origin, value, 0),
+            (NodeKey::Branch(old_children), NodeKey::Branch(new_children)) => {
+                let half = size / 2;
+                for i in 0..8 {
+                    let offset =
+                        IVec3::new((i & 1) as i32, ((i >> 1) & 1) as i32, ((i >> 2) & 1) as i32);
+                    self.render_diff(
+                        renderer,
+                        old_children[i],
+                        new_children[i],
+                        origin + offset * half,
+                    );
+                }
+            }
+            (NodeKey::Leaf(_), NodeKey::Branch(_)) => unreachable!("nodes of the same level"),
+        }
+    }
+}
+
+impl Sim for CellsHashlife {
+    fn update(&mut self, rule: &Rule, _task_pool: &TaskPool) {
+        if self.rule.as_ref() != Some(rule) {
+            self.results.clear();
+            self.rule = Some(rule.clone());
+        }
+        self.tick();
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        renderer.clear();
+        self.render_node(renderer, self.root, self.origin);
+    }
+
+    // a settled universe keeps its level and origin from tick to tick, only
+    // growing it or collecting garbage makes for a full redraw.
+    fn render_changes(&mut self, renderer: &mut CellRenderer, full: bool) {
+        match self.drawn {
+            Some((root, origin))
+                if !full && origin == self.origin && self.level(root) == self.level(self.root) =>
+            {
+                self.render_diff(renderer, root, self.root, origin);
+            }
+            _ => self.render(renderer),
+        }
+        self.drawn = Some((self.root, self.origin));
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::Slider::new(&mut self.step_exponent, 0..=MAX_STEP_EXPONENT).text("step exponent"),
+        );
+        ui.label(format!("generation: {}", self.generation));
+        ui.label(format!("nodes: {}", self.nodes.len()));
+    }
+
+    fn export_loss(&self) -> Option<&'static str> {
+        Some("cells that left the bounds are dropped")
+    }
+
+    fn capabilities(&self) -> SimCapabilities {
+        // empty space would come alive, which an unbounded world can't represent.
+        SimCapabilities {
+            birth_on_zero: false,
+            ..Default::default()
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        let bounds = self.bounding_size;
+        let mut cells = vec![];
+        utils::make_some_noise_default(utils::center(bounds), |pos| {
+            if utils::in_bounds(pos, bounds) {
+                cells.push(pos);
+            }
+        });
+        for pos in cells {
+            self.set_world_cell(pos, rule.states);
+        }
+    }
+
+    fn set_cell(&mut self, _rule: &Rule, pos: IVec3, value: u8) {
+        self.set_world_cell(pos, value);
+    }
+
+    fn get_cell(&self, pos: IVec3) -> u8 {
+        let size = Self::size(self.level(self.root));
+        let local = pos - self.origin;
+        if local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(size)).all() {
+            self.cell(self.root, local)
+        } else {
+            0
+        }
+    }
+
+    fn generations_per_update(&self) -> u64 {
+        1 << self.step_exponent
+    }
+
+    fn cell_count(&self) -> usize {
+        self.nodes[self.root as usize].population as usize
+    }
+
+    fn bounds(&self) -> i32 {
+        self.bounding_size
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.bounding_size {
+            let step_exponent = self.step_exponent;
+            *self = CellsHashlife::new();
+            self.step_exponent = step_exponent;
+        }
+        self.bounding_size = new_bounds;
+        new_bounds
+    }
+}
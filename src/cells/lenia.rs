@@ -0,0 +1,215 @@
+use bevy::{prelude::*, tasks::TaskPool};
+use bevy_egui::egui;
+use rand::Rng;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{Sim, SimCapabilities},
+    rule::Rule,
+    utils,
+};
+
+// cells below this are considered empty when rendering.
+const VISIBLE_THRESHOLD: f32 = 0.05;
+// multiply-adds a generation may take before the world gets too slow to
+// watch.
+const CONVOLUTION_BUDGET: f32 = 1.5e8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LeniaParams {
+    pub radius: i32,
+    pub mu: f32,
+    pub sigma: f32,
+    pub dt: f32,
+}
+
+impl Default for LeniaParams {
+    fn default() -> Self {
+        Self {
+            radius: 5,
+            mu: 0.15,
+            sigma: 0.017,
+            dt: 0.1,
+        }
+    }
+}
+
+impl LeniaParams {
+    fn growth(&self, potential: f32) -> f32 {
+        let x = (potential - self.mu) / self.sigma;
+        2.0 * (-x * x / 2.0).exp() - 1.0
+    }
+}
+
+pub struct CellsLenia {
+    field: Vec<f32>,
+    next: Vec<f32>,
+    kernel: Vec<(IVec3, f32)>,
+    kernel_radius: i32,
+    params: LeniaParams,
+    bounding_size: i32,
+    states: u8,
+}
+
+impl CellsLenia {
+    pub fn new() -> Self {
+        Self {
+            field: vec![],
+            next: vec![],
+            kernel: vec![],
+            kernel_radius: 0,
+            params: LeniaParams::default(),
+            bounding_size: 0,
+            states: 1,
+        }
+    }
+
+    fn build_kernel(&mut self) {
+        let radius = self.params.radius;
+        self.kernel.clear();
+        for z in -radius..=radius {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    let offset = IVec3::new(x, y, z);
+                    let r = offset.as_vec3().length() / radius as f32;
+                    if r > 0.0 && r < 1.0 {
+                        // smooth bump shell, peaking halfway out.
+                        let weight = (4.0 - 1.0 / (r * (1.0 - r))).exp();
+                        self.kernel.push((offset, weight));
+                    }
+                }
+            }
+        }
+
+        let total: f32 = self.kernel.iter().map(|(_, w)| w).sum();
+        for (_, weight) in self.kernel.iter_mut() {
+            *weight /= total;
+        }
+        self.kernel_radius = radius;
+    }
+
+    pub fn tick(&mut self, task_pool: &TaskPool) {
+        if self.kernel_radius != self.params.radius {
+            self.build_kernel();
+        }
+
+        let bounds = self.bounding_size;
+        let slice = (bounds * bounds) as usize;
+        let field = &self.field;
+        let kernel = &self.kernel;
+        let params = self.params;
+
+        task_pool.scope(|s| {
+            for (z, layer) in self.next.chunks_mut(slice).enumerate() {
+                s.spawn(async move {
+                    for (i, cell) in layer.iter_mut().enumerate() {
+                        let idx = z * slice + i;
+                        let pos = utils::idx_to_pos(idx, bounds);
+                        let potential: f32 = kernel
+                            .iter()
+                            .map(|(offset, weight)| {
+                                let neighbor = utils::wrap(pos + *offset, bounds);
+                                field[utils::pos_to_idx(neighbor, bounds)] * weight
+                            })
+                            .sum();
+                        *cell = (field[idx] + params.dt * params.growth(potential)).clamp(0.0, 1.0);
+                    }
+                });
+            }
+        });
+
+        std::mem::swap(&mut self.field, &mut self.next);
+    }
+}
+
+impl Sim for CellsLenia {
+    fn update(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        self.states = rule.states.max(1);
+        self.tick(task_pool);
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        for (idx, value) in self.field.iter().enumerate() {
+            let state = if *value > VISIBLE_THRESHOLD {
+                ((value * self.states as f32).ceil() as u8).clamp(1, self.states)
+            } else {
+                0
+            };
+            renderer.set(idx, state, 0);
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.params.radius, 2..=10).text("kernel radius"));
+        ui.add(egui::Slider::new(&mut self.params.mu, 0.0..=0.5).text("μ"));
+        ui.add(egui::Slider::new(&mut self.params.sigma, 0.001..=0.1).text("σ"));
+        ui.add(egui::Slider::new(&mut self.params.dt, 0.01..=1.0).text("dt"));
+        if ui.button("default parameters").clicked() {
+            self.params = LeniaParams::default();
+        }
+    }
+
+    fn export_loss(&self) -> Option<&'static str> {
+        Some("the continuous field is rounded to whole states")
+    }
+
+    fn capabilities(&self) -> SimCapabilities {
+        // every cell convolves the whole kernel, so a generation costs
+        // bounds³ times the kernel's cells and a larger kernel leaves room for
+        // a smaller world.
+        let kernel_cells = 4.0 / 3.0 * std::f32::consts::PI * (self.params.radius as f32).powi(3);
+        SimCapabilities {
+            max_bounds: (CONVOLUTION_BUDGET / kernel_cells).cbrt() as i32,
+            multi_threaded: true,
+            ..Default::default()
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        self.states = rule.states.max(1);
+        let bounds = self.bounding_size;
+        let field = &mut self.field;
+        let mut rand = rand::thread_rng();
+        utils::make_some_noise_default(utils::center(bounds), |pos| {
+            if utils::in_bounds(pos, bounds) {
+                field[utils::pos_to_idx(pos, bounds)] = rand.gen_range(0.0..=1.0);
+            }
+        });
+    }
+
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: u8) {
+        self.states = rule.states.max(1);
+        let idx = utils::pos_to_idx(pos, self.bounding_size);
+        self.field[idx] = (value as f32 / self.states as f32).min(1.0);
+    }
+
+    fn get_cell(&self, pos: IVec3) -> u8 {
+        let value = self.field[utils::pos_to_idx(pos, self.bounding_size)];
+        if value > VISIBLE_THRESHOLD {
+            ((value * self.states as f32).ceil() as u8).clamp(1, self.states)
+        } else {
+            0
+        }
+    }
+
+    fn cell_count(&self) -> usize {
+        self.field
+            .iter()
+            .filter(|value| **value > VISIBLE_THRESHOLD)
+            .count()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.bounding_size
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.bounding_size {
+            let count = new_bounds.pow(3) as usize;
+            self.field = vec![0.0; count];
+            self.next = vec![0.0; count];
+        }
+        self.bounding_size = new_bounds;
+        new_bounds
+    }
+}
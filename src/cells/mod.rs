@@ -1,26 +1,263 @@
-use bevy::tasks::{TaskPool};
-use crate::{rule::Rule, cell_renderer::CellRenderer};
+use bevy::{prelude::*, tasks::{TaskPool}};
+use bevy_egui::egui;
+use crate::{rule::Rule, cell_renderer::CellRenderer, neighbors::NeighborMethod, utils};
 
 pub mod sims;
 pub use sims::*;
 
+pub mod bit_packed;
+pub mod chunked;
+pub mod hashlife;
+pub mod lenia;
+pub mod second_order;
 pub mod tantan;
 // pub mod leddoo;
 
+pub fn default_sims() -> Vec<(String, Box<dyn Sim>)> {
+    vec![
+        (
+            "tantan single-threaded".into(),
+            Box::new(tantan::CellsSingleThreaded::new()),
+        ),
+        ("lenia".into(), Box::new(lenia::CellsLenia::new())),
+        (
+            "second-order reversible".into(),
+            Box::new(second_order::CellsSecondOrder::new()),
+        ),
+        ("hashlife".into(), Box::new(hashlife::CellsHashlife::new())),
+        ("chunked".into(), Box::new(chunked::CellsChunked::new())),
+        (
+            "bit-packed (2 states)".into(),
+            Box::new(bit_packed::CellsBitPacked::new()),
+        ),
+    ]
+}
+
 pub trait Sim: Send + Sync {
     fn update(&mut self, rule: &Rule, task_pool: &TaskPool);
     fn render(&self, data: &mut CellRenderer);
 
+    // brings the renderer up to date with what changed since the last call,
+    // or draws everything when `full` is set because it holds something else.
+    // simulators that know which cells they touched override this so only
+    // those are visited. the default still scans every cell, which the dense
+    // ones (lenia, second order) keep since their generations scan every cell
+    // too, and only cells whose value changed reach the renderer's changes.
+    fn render_changes(&mut self, renderer: &mut CellRenderer, _full: bool) {
+        self.render(renderer);
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("no parameters");
+    }
+
     fn reset(&mut self) {
         let bounds = self.bounds();
         self.set_bounds(0);
         self.set_bounds(bounds);
     }
 
+    fn capabilities(&self) -> SimCapabilities {
+        SimCapabilities::default()
+    }
+
     fn spawn_noise(&mut self, rule: &Rule);
 
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: u8);
+
+    fn get_cell(&self, pos: IVec3) -> u8;
+
+    fn export_cells(&self) -> Vec<(IVec3, u8)> {
+        let mut renderer = CellRenderer::new();
+        renderer.set_bounds(self.bounds());
+        self.render(&mut renderer);
+        renderer
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(idx, value)| (utils::idx_to_pos(idx, self.bounds()), *value))
+            .collect()
+    }
+
+    // what `export_cells` leaves out of this simulator's state, saving a
+    // snapshot warns about it.
+    fn export_loss(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn spawn_cells(&mut self, rule: &Rule, cells: &[(IVec3, u8)]) {
+        for (pos, value) in cells {
+            self.set_cell(rule, *pos, *value);
+        }
+    }
+
+    fn import_cells(&mut self, rule: &Rule, cells: &[(IVec3, u8)]) {
+        self.reset();
+        self.spawn_cells(rule, cells);
+    }
+
+    fn generations_per_update(&self) -> u64 {
+        1
+    }
+
     fn cell_count(&self) -> usize;
 
+    fn stats(&self) -> Option<String> {
+        None
+    }
+
     fn bounds(&self) -> i32;
     fn set_bounds(&mut self, new_bounds: i32) -> i32;
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimCapabilities {
+    pub neighbor_methods: &'static [NeighborMethod],
+    pub max_states: u8,
+    pub max_bounds: i32,
+    pub birth_on_zero: bool,
+    pub deterministic: bool,
+    pub multi_threaded: bool,
+}
+
+impl Default for SimCapabilities {
+    fn default() -> Self {
+        Self {
+            neighbor_methods: &[NeighborMethod::Moore, NeighborMethod::VonNeumann],
+            max_states: u8::MAX,
+            max_bounds: i32::MAX,
+            birth_on_zero: true,
+            deterministic: true,
+            multi_threaded: false,
+        }
+    }
+}
+
+impl SimCapabilities {
+    pub fn check(&self, rule: &Rule, bounds: i32) -> Result<(), String> {
+        if !self.neighbor_methods.contains(&rule.neighbor_method) {
+            return Err(format!(
+                "{:?} neighborhood not supported",
+                rule.neighbor_method
+            ));
+        }
+        if rule.states > self.max_states {
+            return Err(format!("supports at most {} states", self.max_states));
+        }
+        if bounds > self.max_bounds {
+            return Err(format!(
+                "supports a bounding size of at most {}",
+                self.max_bounds
+            ));
+        }
+        if !self.birth_on_zero && rule.birth_rule.in_range(0) {
+            return Err("can't birth cells with 0 neighbors".into());
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        let neighbor_methods: Vec<String> = self
+            .neighbor_methods
+            .iter()
+            .map(|method| format!("{:?}", method))
+            .collect();
+        let max_bounds = if self.max_bounds < MAX_BOUNDS {
+            format!(", bounding size up to {}", self.max_bounds)
+        } else {
+            String::new()
+        };
+        format!(
+            "{} neighborhood, up to {} states{}, {}, {}",
+            neighbor_methods.join(" or "),
+            self.max_states,
+            max_bounds,
+            if self.multi_threaded {
+                "multi-threaded"
+            } else {
+                "single-threaded"
+            },
+            if self.deterministic {
+                "deterministic"
+            } else {
+                "non-deterministic"
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rule::Value, snapshot::Snapshot};
+
+    // drawing only the changes has to end up where a full redraw does, across
+    // generations, edits, spawned and imported cells and a reset.
+    #[test]
+    fn render_changes_matches_render() {
+        let rule = Rule {
+            survival_rule: Value::new(&[2, 6, 9]),
+            birth_rule: Value::new(&[4, 6, 8, 9, 10]),
+            states: 2,
+            neighbor_method: NeighborMethod::Moore,
+        };
+        let bounds = 32;
+        let task_pool = TaskPool::new();
+        let center = utils::center(bounds);
+        let cells: Vec<(IVec3, u8)> = (0..4)
+            .map(|i| (center + IVec3::new(i, 1, -i), rule.states))
+            .collect();
+
+        for (name, mut sim) in default_sims() {
+            sim.set_bounds(bounds);
+            sim.spawn_noise(&rule);
+            let mut renderer = CellRenderer::new();
+            renderer.set_bounds(bounds);
+            sim.render_changes(&mut renderer, true);
+
+            for generation in 0..12 {
+                match generation {
+                    4 => sim.set_cell(&rule, center, 0),
+                    6 => sim.spawn_cells(&rule, &cells),
+                    8 => {
+                        sim.reset();
+                        sim.spawn_noise(&rule);
+                    }
+                    10 => sim.import_cells(&rule, &cells),
+                    _ => sim.update(&rule, &task_pool),
+                }
+                sim.render_changes(&mut renderer, false);
+
+                let mut expected = CellRenderer::new();
+                expected.set_bounds(bounds);
+                sim.render(&mut expected);
+                assert!(
+                    renderer.values == expected.values && renderer.neighbors == expected.neighbors,
+                    "{} differs after generation {}",
+                    name,
+                    generation
+                );
+            }
+        }
+    }
+
+    // snapshots may hold worlds smaller than the noise cube, whose cells
+    // outside the bounds must be dropped rather than indexed.
+    #[test]
+    fn small_snapshot_spawns_noise() {
+        let text = "bounds 8\nrule 4/4/2/M\ngeneration 3\n1 2 3 1\n";
+        let snapshot = Snapshot::read_text(&mut text.as_bytes()).unwrap();
+        assert_eq!(snapshot.bounds, 8);
+
+        for (_, mut sim) in default_sims() {
+            sim.set_bounds(snapshot.bounds);
+            sim.import_cells(&snapshot.rule, &snapshot.cells);
+            sim.spawn_noise(&snapshot.rule);
+            let mut renderer = CellRenderer::new();
+            renderer.set_bounds(snapshot.bounds);
+            sim.render_changes(&mut renderer, true);
+            assert!(sim.cell_count() > 0);
+        }
+    }
+}
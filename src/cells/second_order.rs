@@ -0,0 +1,183 @@
+use bevy::{prelude::*, tasks::TaskPool};
+use bevy_egui::egui;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{Sim, SimCapabilities},
+    rule::Rule,
+    utils,
+};
+
+// second-order (Fredkin style) automaton: next = f(current) xor previous.
+// any totalistic rule becomes reversible, since previous = f(current) xor next.
+pub struct CellsSecondOrder {
+    current: Vec<bool>,
+    previous: Vec<bool>,
+    bounding_size: i32,
+    states: u8,
+    backwards: bool,
+}
+
+impl CellsSecondOrder {
+    pub fn new() -> Self {
+        Self {
+            current: vec![],
+            previous: vec![],
+            bounding_size: 0,
+            states: 1,
+            backwards: false,
+        }
+    }
+
+    pub fn step_forward(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        let next = self.next_generation(rule, task_pool);
+        self.previous = std::mem::replace(&mut self.current, next);
+    }
+
+    pub fn step_backward(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        std::mem::swap(&mut self.current, &mut self.previous);
+        self.step_forward(rule, task_pool);
+        std::mem::swap(&mut self.current, &mut self.previous);
+    }
+
+    fn next_generation(&self, rule: &Rule, task_pool: &TaskPool) -> Vec<bool> {
+        let bounds = self.bounding_size;
+        let slice = (bounds * bounds) as usize;
+        let mut next = vec![false; self.current.len()];
+        let current = &self.current;
+        let previous = &self.previous;
+        let neighbor_iter = rule.neighbor_method.get_neighbor_iter();
+
+        task_pool.scope(|s| {
+            for (z, layer) in next.chunks_mut(slice).enumerate() {
+                s.spawn(async move {
+                    for (i, cell) in layer.iter_mut().enumerate() {
+                        let idx = z * slice + i;
+                        let pos = utils::idx_to_pos(idx, bounds);
+                        let neighbors = neighbor_iter
+                            .iter()
+                            .filter(|offset| {
+                                let neighbor = utils::wrap(pos + **offset, bounds);
+                                current[utils::pos_to_idx(neighbor, bounds)]
+                            })
+                            .count() as u8;
+                        let alive = if current[idx] {
+                            rule.survival_rule.in_range(neighbors)
+                        } else {
+                            rule.birth_rule.in_range(neighbors)
+                        };
+                        *cell = alive ^ previous[idx];
+                    }
+                });
+            }
+        });
+
+        next
+    }
+}
+
+impl Sim for CellsSecondOrder {
+    fn update(&mut self, rule: &Rule, task_pool: &TaskPool) {
+        self.states = rule.states.max(1);
+        if self.backwards {
+            self.step_backward(rule, task_pool);
+        } else {
+            self.step_forward(rule, task_pool);
+        }
+    }
+
+    fn render(&self, renderer: &mut CellRenderer) {
+        for (idx, alive) in self.current.iter().enumerate() {
+            renderer.set(idx, if *alive { self.states } else { 0 }, 0);
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.toggle_value(&mut self.backwards, "run backwards");
+    }
+
+    fn export_loss(&self) -> Option<&'static str> {
+        Some("the previous generation is not saved, so it can't be run backwards past the load")
+    }
+
+    fn capabilities(&self) -> SimCapabilities {
+        SimCapabilities {
+            multi_threaded: true,
+            ..Default::default()
+        }
+    }
+
+    fn spawn_noise(&mut self, rule: &Rule) {
+        self.states = rule.states.max(1);
+        let bounds = self.bounding_size;
+        let current = &mut self.current;
+        utils::make_some_noise_default(utils::center(bounds), |pos| {
+            if utils::in_bounds(pos, bounds) {
+                current[utils::pos_to_idx(pos, bounds)] = true;
+            }
+        });
+    }
+
+    fn set_cell(&mut self, rule: &Rule, pos: IVec3, value: u8) {
+        self.states = rule.states.max(1);
+        let idx = utils::pos_to_idx(pos, self.bounding_size);
+        self.current[idx] = value != 0;
+    }
+
+    fn get_cell(&self, pos: IVec3) -> u8 {
+        if self.current[utils::pos_to_idx(pos, self.bounding_size)] {
+            self.states
+        } else {
+            0
+        }
+    }
+
+    fn cell_count(&self) -> usize {
+        self.current.iter().filter(|alive| **alive).count()
+    }
+
+    fn bounds(&self) -> i32 {
+        self.bounding_size
+    }
+
+    fn set_bounds(&mut self, new_bounds: i32) -> i32 {
+        if new_bounds != self.bounding_size {
+            let count = new_bounds.pow(3) as usize;
+            self.current = vec![false; count];
+            self.previous = vec![false; count];
+        }
+        self.bounding_size = new_bounds;
+        new_bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{neighbors::NeighborMethod, rule::Value};
+
+    #[test]
+    fn steps_backward_to_the_seed() {
+        let rule = Rule {
+            survival_rule: Value::new(&[2, 6, 9]),
+            birth_rule: Value::new(&[4, 6, 8, 9, 10]),
+            states: 1,
+            neighbor_method: NeighborMethod::Moore,
+        };
+        let task_pool = TaskPool::new();
+        let mut sim = CellsSecondOrder::new();
+        sim.set_bounds(16);
+        sim.spawn_noise(&rule);
+        let seed = sim.current.clone();
+
+        for _ in 0..20 {
+            sim.step_forward(&rule, &task_pool);
+        }
+        assert_ne!(sim.current, seed);
+        for _ in 0..20 {
+            sim.step_backward(&rule, &task_pool);
+        }
+        assert_eq!(sim.current, seed);
+        assert!(sim.previous.iter().all(|alive| !alive));
+    }
+}
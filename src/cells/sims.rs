@@ -1,15 +1,36 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
 use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
 use bevy_egui::{egui, EguiContext};
 
 use crate::{
-    cell_renderer::{CellRenderer, InstanceData, InstanceMaterialData},
+    brush::{self, Brush},
+    cell_renderer::{CellRenderer, InstanceData, InstanceMaterialData, Population},
     cells::Sim,
     color_method::ColorMethod,
-    neighbors::NeighborMethod,
+    components::Components,
+    cutaway::Cutaway,
+    explorer::Explorer,
+    export::{self, mesh::CellMesh},
+    history::History,
+    import,
+    neighbors::{NeighborMethod, VONNEUMANN_NEIGHBORS},
+    periodicity::PeriodDetector,
+    rle::Pattern,
     rule::Rule,
+    snapshot::Snapshot,
+    stats::Stats,
+    symmetry::{self, SymmetryGroup},
     utils,
 };
 
+// the largest world the ui offers, files asking for more are rejected.
+pub const MAX_BOUNDS: i32 = 128;
+
 #[derive(Clone)]
 pub struct Example {
     pub name: String,
@@ -19,10 +40,33 @@ pub struct Example {
     pub color2: Color,
 }
 
+// everything besides the cells themselves that decides what the instances
+// look like, any change to it rebuilds all of them.
+#[derive(Clone, PartialEq)]
+struct InstanceView {
+    bounds: i32,
+    states: u8,
+    color_method: ColorMethod,
+    color1: Color,
+    color2: Color,
+    cutaway: Cutaway,
+    cull_hidden: bool,
+}
+
 pub struct Sims {
     sims: Vec<(String, Box<dyn Sim>)>,
     active_sim: usize,
     bounds: i32,
+    generation: u64,
+    paused: bool,
+    history: History,
+    stats: Stats,
+    periodicity: PeriodDetector,
+    components: Components,
+    analyze_components: bool,
+    symmetry_group: SymmetryGroup,
+    // the group the score was measured for, None once the world was edited.
+    symmetry_score: Option<(SymmetryGroup, f32)>,
     update_dt: std::time::Duration,
 
     renderer: Option<Box<CellRenderer>>,
@@ -33,6 +77,26 @@ pub struct Sims {
     color2: Color,
 
     examples: Vec<Example>,
+    explorer: Explorer,
+    brush: Brush,
+    cutaway: Cutaway,
+    // the cutaway's `surface_depths` for the drawn world, while the onion is on.
+    surface_depths: Vec<u8>,
+    cull_hidden: bool,
+    instance_counts: (usize, usize),
+    // which cells were skipped for being enclosed, kept so instances can be
+    // updated one changed cell at a time.
+    culled: Vec<bool>,
+    culled_count: usize,
+    instance_view: Option<InstanceView>,
+    // the sim and bounds the renderer was last drawn with.
+    rendered: Option<(usize, i32)>,
+
+    snapshot_path: String,
+    model_path: String,
+    pattern_text: String,
+    export_path: String,
+    status: Option<String>,
 }
 
 impl Sims {
@@ -41,6 +105,15 @@ impl Sims {
             sims: vec![],
             active_sim: usize::MAX,
             bounds: 64,
+            generation: 0,
+            paused: false,
+            history: History::new(256),
+            stats: Stats::new(1024),
+            periodicity: PeriodDetector::new(4096),
+            components: Components::new(),
+            analyze_components: false,
+            symmetry_group: SymmetryGroup::None,
+            symmetry_score: None,
             update_dt: std::time::Duration::from_secs(0),
             renderer: Some(Box::new(CellRenderer::new())),
             rule: None,
@@ -48,9 +121,32 @@ impl Sims {
             color1: Color::YELLOW,
             color2: Color::RED,
             examples: vec![],
+            explorer: Explorer::new(),
+            brush: Brush::new(),
+            cutaway: Cutaway::new(),
+            surface_depths: vec![],
+            cull_hidden: true,
+            instance_counts: (0, 0),
+            culled: vec![],
+            culled_count: 0,
+            instance_view: None,
+            rendered: None,
+            snapshot_path: "snapshot.rsa".into(),
+            model_path: "model.vox".into(),
+            pattern_text: String::new(),
+            export_path: "export.vox".into(),
+            status: None,
         }
     }
 
+    fn restart(&mut self) {
+        self.generation = 0;
+        self.history.clear();
+        self.stats.clear();
+        self.periodicity.clear();
+        self.symmetry_score = None;
+    }
+
     pub fn add_sim(&mut self, name: String, sim: Box<dyn Sim>) {
         self.sims.push((name, sim));
     }
@@ -66,6 +162,7 @@ impl Sims {
 
         let rule = self.rule.take().expect("taking rule");
         self.active_sim = idx;
+        self.restart();
         self.bounds = self.sims[idx].1.set_bounds(self.bounds);
         self.sims[idx].1.spawn_noise(&rule);
         self.renderer
@@ -81,6 +178,7 @@ impl Sims {
         self.color_method = example.color_method;
         self.color1 = example.color1;
         self.color2 = example.color2;
+        self.restart();
 
         if self.active_sim < self.sims.len() {
             let sim = &mut self.sims[self.active_sim].1;
@@ -89,6 +187,220 @@ impl Sims {
         }
         self.rule = Some(rule);
     }
+
+    // what to draw for a cell, and whether it was left out only for being
+    // boxed in by its neighbors.
+    fn cell_instance(
+        &self,
+        states: u8,
+        renderer: &CellRenderer,
+        idx: usize,
+    ) -> (Option<InstanceData>, bool) {
+        let bounds = renderer.bounds;
+        let cutaway = self.cutaway.is_active();
+        let drawn = |pos: IVec3| {
+            if !utils::in_bounds(pos, bounds) {
+                return false;
+            }
+            let idx = utils::pos_to_idx(pos, bounds);
+            let surface_depth = self.surface_depths.get(idx).copied().unwrap_or(0);
+            renderer.values[idx] != 0 && (!cutaway || self.cutaway.visible(pos, surface_depth))
+        };
+
+        let pos = utils::idx_to_pos(idx, bounds);
+        if !drawn(pos) {
+            return (None, false);
+        }
+        // nothing of a cell boxed in on all six faces can be seen.
+        if self.cull_hidden && VONNEUMANN_NEIGHBORS.iter().all(|dir| drawn(pos + *dir)) {
+            return (None, true);
+        }
+        let instance = InstanceData {
+            position: (pos - utils::center(bounds)).as_vec3(),
+            scale: 1.0,
+            color: self.cell_color(states, renderer, idx).into(),
+        };
+        (Some(instance), false)
+    }
+
+    pub fn cell_color(&self, states: u8, renderer: &CellRenderer, idx: usize) -> Color {
+        let pos = utils::idx_to_pos(idx, renderer.bounds);
+        self.color_method.color(
+            self.color1,
+            self.color2,
+            states,
+            renderer.values[idx],
+            renderer.neighbors[idx],
+            utils::dist_to_center(pos, renderer.bounds),
+            self.components.labels.get(idx).copied().unwrap_or(0),
+        )
+    }
+
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        let renderer = self.renderer.as_ref().expect("get renderer");
+        let states = self.rule.as_ref().expect("getting rule").states;
+        let cells: Vec<(IVec3, Color)> = (0..renderer.cell_count())
+            .filter(|idx| renderer.values[*idx] != 0)
+            .map(|idx| {
+                let pos = utils::idx_to_pos(idx, renderer.bounds);
+                (pos, self.cell_color(states, renderer, idx))
+            })
+            .collect();
+
+        let mut w = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("vox") => export::vox::write(&mut w, renderer.bounds, &cells)?,
+            Some("obj") => CellMesh::new(renderer.bounds, &cells).write_obj(&mut w)?,
+            Some("ply") => CellMesh::new(renderer.bounds, &cells).write_ply(&mut w)?,
+            Some("stl") => CellMesh::new(renderer.bounds, &cells).write_stl(&mut w)?,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "unsupported export format, expected .vox, .obj, .ply or .stl",
+                ))
+            }
+        }
+        w.flush()
+    }
+
+    pub fn load_model(&mut self, path: &Path) -> io::Result<()> {
+        let rule = self.rule.as_ref().expect("getting rule");
+        let cells = import::load(path, self.bounds, rule.states)?;
+
+        let sim = &mut self.sims[self.active_sim].1;
+        sim.reset();
+        sim.spawn_cells(rule, &cells);
+        self.restart();
+        Ok(())
+    }
+
+    pub fn pattern(&self) -> String {
+        let cells = self.sims[self.active_sim].1.export_cells();
+        Pattern::new(self.rule.clone(), &cells).to_rle()
+    }
+
+    // places a pattern in the middle of the world, switching to its rule
+    // if it has one.
+    pub fn load_pattern(&mut self, text: &str) -> Result<(), String> {
+        let pattern = Pattern::parse(text)?;
+        let rule = pattern
+            .rule
+            .unwrap_or_else(|| self.rule.clone().expect("getting rule"));
+        let cells: Vec<_> = pattern
+            .cells
+            .into_iter()
+            .map(|(pos, value)| (pos, value.min(rule.states)))
+            .collect();
+        let cells = import::center(cells, self.bounds);
+
+        let sim = &mut self.sims[self.active_sim].1;
+        sim.reset();
+        sim.spawn_cells(&rule, &cells);
+        self.rule = Some(rule);
+        self.restart();
+        Ok(())
+    }
+
+    // the live cells as last rendered, around the world origin like the
+    // instances.
+    pub fn population(&self) -> Option<Population> {
+        let renderer = self.renderer.as_ref()?;
+        let mut population = renderer.population();
+        population.center_of_mass -= utils::center(renderer.bounds).as_vec3();
+        Some(population)
+    }
+
+    // aims the brush along a ray from the camera and, while paused, paints in
+    // front of the cell it hits or erases around it.
+    pub fn brush_stroke(&mut self, origin: Vec3, direction: Vec3, paint: bool, erase: bool) {
+        // the brush can run before `update` picked the first sim.
+        if self.active_sim >= self.sims.len() {
+            return;
+        }
+        let bounds = self.bounds;
+        // cells are drawn around the world origin, with cell centers on integers.
+        let offset = utils::center(bounds).as_vec3() + Vec3::splat(0.5);
+        let origin = origin + offset;
+
+        let sim = &self.sims[self.active_sim].1;
+        let mut hovered = brush::cast(origin, direction, bounds, |pos| sim.get_cell(pos) != 0);
+        if hovered.is_none() {
+            // nothing alive under the cursor, aim at the point closest to the center.
+            let t = (offset - origin).dot(direction).max(0.0);
+            let pos = (origin + direction * t).floor().as_ivec3();
+            if utils::in_bounds(pos, bounds) {
+                hovered = Some((pos, pos));
+            }
+        }
+        self.brush.hovered = hovered;
+
+        let (hit, front) = match hovered {
+            Some(hovered) if self.paused => hovered,
+            _ => return,
+        };
+        let rule = self.rule.as_ref().expect("getting rule");
+        let (center, value) = if erase {
+            (hit, 0)
+        } else if paint {
+            (front, self.brush.state.min(rule.states))
+        } else {
+            return;
+        };
+
+        let cells: Vec<_> = self
+            .brush
+            .cells(center, bounds)
+            .into_iter()
+            .map(|pos| (pos, value))
+            .collect();
+        self.sims[self.active_sim].1.spawn_cells(rule, &cells);
+        self.symmetry_score = None;
+    }
+
+    pub fn save_snapshot(&self, path: &Path) -> io::Result<()> {
+        if path.extension().and_then(|ext| ext.to_str()) == Some("rle") {
+            return fs::write(path, self.pattern());
+        }
+
+        let snapshot = Snapshot {
+            bounds: self.bounds,
+            rule: self.rule.clone().expect("getting rule"),
+            generation: self.generation,
+            cells: self.sims[self.active_sim].1.export_cells(),
+        };
+        snapshot.save(path)
+    }
+
+    pub fn load_snapshot(&mut self, path: &Path) -> io::Result<()> {
+        if path.extension().and_then(|ext| ext.to_str()) == Some("rle") {
+            let text = fs::read_to_string(path)?;
+            return self.load_pattern(&text).map_err(utils::invalid_data);
+        }
+
+        let snapshot = Snapshot::load(path)?;
+        let sim = &mut self.sims[self.active_sim].1;
+        self.bounds = sim.set_bounds(snapshot.bounds);
+
+        let bounds = self.bounds;
+        let cells: Vec<_> = snapshot
+            .cells
+            .into_iter()
+            .filter(|(pos, _)| utils::in_bounds(*pos, bounds))
+            .collect();
+        sim.import_cells(&snapshot.rule, &cells);
+
+        self.renderer
+            .as_mut()
+            .expect("get renderer")
+            .set_bounds(self.bounds);
+        self.history.clear();
+        self.stats.clear();
+        self.periodicity.clear();
+        self.symmetry_score = None;
+        self.generation = snapshot.generation;
+        self.rule = Some(snapshot.rule);
+        Ok(())
+    }
 }
 
 pub fn update(
@@ -100,6 +412,10 @@ pub fn update(
         this.set_sim(0);
     }
 
+    for example in this.explorer.poll() {
+        this.add_example(example);
+    }
+
     let mut bounds = this.bounds;
     let mut active_sim = this.active_sim;
 
@@ -112,8 +428,22 @@ pub fn update(
             egui::ComboBox::from_id_source("simulator")
                 .selected_text(&this.sims[active_sim].0)
                 .show_ui(ui, |ui| {
-                    for (i, (name, _)) in this.sims.iter().enumerate() {
-                        ui.selectable_value(&mut active_sim, i, name);
+                    let rule = this.rule.as_ref().expect("getting rule");
+                    for (i, (name, sim)) in this.sims.iter().enumerate() {
+                        let capabilities = sim.capabilities();
+                        let supported = capabilities.check(rule, bounds);
+                        let response = ui
+                            .add_enabled(
+                                supported.is_ok(),
+                                egui::SelectableLabel::new(active_sim == i, name),
+                            )
+                            .on_hover_text(capabilities.describe());
+                        if response.clicked() {
+                            active_sim = i;
+                        }
+                        if let Err(reason) = supported {
+                            response.on_disabled_hover_text(reason);
+                        }
                     }
                 });
 
@@ -123,35 +453,125 @@ pub fn update(
             }
 
             let update_dt = this.update_dt;
+            let generation = this.generation;
+            let periodicity = this.periodicity.result();
             let rule = this.rule.take().expect("taking rule");
             let sim = &mut this.sims[active_sim].1;
 
             let cell_count = sim.cell_count();
             ui.label(format!("cells: {}", cell_count));
+            ui.label(format!("generation: {}", generation));
+            if let Some(periodicity) = periodicity {
+                ui.label(format!("pattern: {}", periodicity));
+            }
             ui.label(format!(
                 "update: {:.2?} per cell",
                 update_dt / cell_count.max(1) as u32
             ));
+            if let Some(stats) = sim.stats() {
+                ui.label(stats);
+            }
+            if let Err(reason) = sim.capabilities().check(&rule, bounds) {
+                ui.colored_label(egui::Color32::RED, reason);
+            }
 
+            let mut restarted = false;
+            let mut spawned = false;
             if ui.button("reset").clicked() {
                 sim.reset();
+                restarted = true;
             }
 
             if ui.button("spawn noise").clicked() {
                 sim.spawn_noise(&rule);
+                spawned = true;
             }
 
-            ui.add(egui::Slider::new(&mut bounds, 32..=128).text("bounding size"));
+            ui.add(egui::Slider::new(&mut bounds, 32..=MAX_BOUNDS).text("bounding size"));
             if bounds != old_bounds {
                 bounds = sim.set_bounds(bounds);
                 sim.spawn_noise(&rule);
+                restarted = true;
                 this.renderer
                     .as_mut()
                     .expect("get renderer")
                     .set_bounds(bounds);
             }
 
+            if restarted {
+                this.restart();
+            } else if spawned {
+                this.symmetry_score = None;
+            }
+
+            ui.checkbox(&mut this.paused, "paused");
+
+            let mut capacity = this.history.capacity();
+            ui.add(egui::Slider::new(&mut capacity, 0..=1024).text("history size"));
+            if capacity != this.history.capacity() {
+                this.history.set_capacity(capacity);
+            }
+
+            if this.history.len() > 1 {
+                let mut position = this.history.position();
+                let latest = this.history.len() - 1;
+                // the history only keeps what the renderer shows, restoring it
+                // would silently drop whatever else this sim's state holds.
+                let loss = this.sims[active_sim].1.export_loss();
+                let response = ui.add_enabled(
+                    loss.is_none(),
+                    egui::Slider::new(&mut position, 0..=latest).text("timeline"),
+                );
+                if let Some(loss) = loss {
+                    response.on_disabled_hover_text(format!("can't restore generations: {}", loss));
+                }
+                if position != this.history.position() {
+                    this.paused = true;
+                    this.history.seek(position);
+                    let cells = this.history.cells();
+                    this.sims[active_sim].1.import_cells(&rule, &cells);
+                    this.symmetry_score = None;
+                    this.generation = this.history.generation().expect("getting generation");
+                }
+            }
+
             this.rule = Some(rule);
+
+            ui.checkbox(&mut this.analyze_components, "analyze components");
+            if this.analyze_components || this.color_method == ColorMethod::Component {
+                let components = &this.components;
+                ui.label(format!(
+                    "components: {}, largest: {}",
+                    components.count(),
+                    components.largest()
+                ));
+                ui.collapsing("size distribution", |ui| {
+                    for (size, count) in components.size_distribution().iter().rev() {
+                        ui.label(format!("{} cells: {}", size, count));
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("symmetry")
+                    .selected_text(this.symmetry_group.name())
+                    .show_ui(ui, |ui| {
+                        for group in SymmetryGroup::ALL {
+                            ui.selectable_value(&mut this.symmetry_group, group, group.name());
+                        }
+                    });
+                if ui.button("spawn symmetric noise").clicked() {
+                    let rule = this.rule.clone().expect("getting rule");
+                    let cells = symmetry::noise(this.symmetry_group, bounds, rule.states);
+                    this.sims[active_sim].1.spawn_cells(&rule, &cells);
+                    this.symmetry_score = None;
+                }
+            });
+            if let Some((group, score)) = this.symmetry_score {
+                if group != SymmetryGroup::None {
+                    ui.label(format!("symmetry: {:.2}", score));
+                }
+            }
         }
 
         ui.add_space(24.0);
@@ -172,6 +592,11 @@ pub fn update(
                         "Distance to Center",
                     );
                     ui.selectable_value(&mut this.color_method, ColorMethod::Neighbor, "Neighbor");
+                    ui.selectable_value(
+                        &mut this.color_method,
+                        ColorMethod::Component,
+                        "Component",
+                    );
                 });
 
             color_picker(ui, &mut this.color1);
@@ -199,11 +624,16 @@ pub fn update(
                 let sim = &mut this.sims[active_sim].1;
                 sim.reset();
                 sim.spawn_noise(&rule);
+                this.restart();
             }
 
             this.rule = Some(rule)
         }
 
+        ui.add_space(24.0);
+        ui.label("Simulator parameters:");
+        this.sims[active_sim].1.ui(ui);
+
         ui.add_space(24.0);
 
         ui.label("Examples:");
@@ -213,48 +643,229 @@ pub fn update(
                 this.set_example(i);
             }
         }
+
+        ui.add_space(24.0);
+
+        ui.label("Cross-section:");
+        this.cutaway.ui(ui, bounds);
+        ui.checkbox(&mut this.cull_hidden, "skip enclosed cells");
+        let (visible, culled) = this.instance_counts;
+        ui.label(format!("drawn: {}, culled: {}", visible, culled));
+
+        ui.add_space(24.0);
+
+        ui.label("Brush:");
+        {
+            let states = this.rule.as_ref().expect("getting rule").states;
+            this.brush.ui(ui, states);
+            ui.label("while paused, ctrl + left click paints and ctrl + right click erases");
+        }
+
+        ui.add_space(24.0);
+
+        ui.label("Explore:");
+        this.explorer.ui(ui);
+
+        ui.add_space(24.0);
+
+        ui.label("Snapshot:");
+        {
+            ui.text_edit_singleline(&mut this.snapshot_path)
+                .on_hover_text(
+                    "paths ending in .txt are saved as text, .rle as a pattern, others as binary",
+                );
+            ui.horizontal(|ui| {
+                let path = std::path::PathBuf::from(&this.snapshot_path);
+                if ui.button("save").clicked() {
+                    let loss = this.sims[this.active_sim].1.export_loss();
+                    this.status = Some(match (this.save_snapshot(&path), loss) {
+                        (Ok(()), Some(loss)) => format!("saved {}, but {}", path.display(), loss),
+                        (Ok(()), None) => format!("saved {}", path.display()),
+                        (Err(err), _) => format!("saving failed: {}", err),
+                    });
+                }
+                if ui.button("load").clicked() {
+                    this.status = Some(match this.load_snapshot(&path) {
+                        Ok(()) => format!("loaded {}", path.display()),
+                        Err(err) => format!("loading failed: {}", err),
+                    });
+                    bounds = this.bounds;
+                }
+            });
+        }
+
+        ui.add_space(24.0);
+
+        ui.label("Pattern:");
+        {
+            ui.text_edit_multiline(&mut this.pattern_text)
+                .on_hover_text("3D RLE, `$` ends a row and `/` ends a layer");
+            ui.horizontal(|ui| {
+                if ui.button("copy").clicked() {
+                    let pattern = this.pattern();
+                    ui.output().copied_text = pattern.clone();
+                    this.pattern_text = pattern;
+                }
+                if ui.button("load pattern").clicked() {
+                    let text = this.pattern_text.clone();
+                    this.status = Some(match this.load_pattern(&text) {
+                        Ok(()) => "loaded pattern".into(),
+                        Err(err) => format!("loading pattern failed: {}", err),
+                    });
+                }
+            });
+        }
+
+        ui.add_space(24.0);
+
+        ui.label("Model:");
+        {
+            ui.text_edit_singleline(&mut this.model_path)
+                .on_hover_text(".vox models, or text files with one `x y z state` per line");
+            if ui.button("load model").clicked() {
+                let path = std::path::PathBuf::from(&this.model_path);
+                this.status = Some(match this.load_model(&path) {
+                    Ok(()) => format!("loaded {}", path.display()),
+                    Err(err) => format!("loading failed: {}", err),
+                });
+            }
+        }
+
+        ui.add_space(24.0);
+
+        ui.label("Export:");
+        {
+            ui.text_edit_singleline(&mut this.export_path)
+                .on_hover_text("the format is picked from the extension: .vox, .obj, .ply or .stl");
+            if ui.button("export").clicked() {
+                let path = std::path::PathBuf::from(&this.export_path);
+                this.status = Some(match this.export(&path) {
+                    Ok(()) => format!("exported {}", path.display()),
+                    Err(err) => format!("exporting failed: {}", err),
+                });
+            }
+        }
+
+        if let Some(status) = &this.status {
+            ui.add_space(24.0);
+            ui.label(status);
+        }
+    });
+
+    egui::Window::new("Statistics").show(egui_ctx.ctx_mut(), |ui| {
+        this.stats.ui(ui);
     });
 
     let rule = this.rule.take().expect("taking rule");
     let mut renderer = this.renderer.take().expect("taking renderer");
 
+    let paused = this.paused;
+    // the renderer only holds what this sim drew as long as neither the sim
+    // nor the bounds changed since.
+    let full = this.rendered != Some((active_sim, renderer.bounds));
     let sim = &mut this.sims[active_sim].1;
 
     let t0 = std::time::Instant::now();
     let task_pool = AsyncComputeTaskPool::get();
-    sim.update(&rule, task_pool);
+    let mut generations = 0;
+    if !paused && sim.capabilities().check(&rule, bounds).is_ok() {
+        sim.update(&rule, task_pool);
+        generations = sim.generations_per_update();
+    }
     let update_dt = t0.elapsed();
 
-    sim.render(&mut renderer);
-
-    let instance_data = &mut query.iter_mut().next().expect("getting instance data").0;
-    instance_data.truncate(0);
-    for idx in 0..renderer.cell_count() {
-        let value = renderer.values[idx];
-        let neighbors = renderer.neighbors[idx];
-
-        if value != 0 {
-            let pos = utils::idx_to_pos(idx, bounds);
-            instance_data.push(InstanceData {
-                position: (pos - utils::center(bounds)).as_vec3(),
-                scale: 1.0,
-                color: this
-                    .color_method
-                    .color(
-                        this.color2,
-                        this.color2,
-                        rule.states,
-                        value,
-                        neighbors,
-                        utils::dist_to_center(pos, bounds),
-                    )
-                    .into(),
-            });
+    sim.render_changes(&mut renderer, full);
+    this.rendered = Some((active_sim, renderer.bounds));
+
+    if this.analyze_components || this.color_method == ColorMethod::Component {
+        this.components.label(&renderer, rule.neighbor_method);
+    }
+    // the score only moves with the world, so a paused world keeps its score.
+    let measured = this.symmetry_score.map(|(group, _)| group);
+    if this.symmetry_group != SymmetryGroup::None
+        && (generations > 0 || full || measured != Some(this.symmetry_group))
+    {
+        let cells = symmetry::live_cells(&renderer);
+        let score = symmetry::measure(this.symmetry_group, &cells);
+        this.symmetry_score = Some((this.symmetry_group, score));
+    }
+
+    let view = InstanceView {
+        bounds,
+        states: rule.states,
+        color_method: this.color_method,
+        color1: this.color1,
+        color2: this.color2,
+        cutaway: this.cutaway.clone(),
+        cull_hidden: this.cull_hidden,
+    };
+    let mut instances = query.iter_mut().next().expect("getting instance data");
+    instances.begin_frame();
+    let changes = renderer.take_changes();
+    if instances.cell_count() != renderer.cell_count() {
+        instances.reset(renderer.cell_count());
+        this.culled = vec![false; renderer.cell_count()];
+        this.culled_count = 0;
+    }
+    // component labels can move all over the world in a single generation.
+    let mut rebuild =
+        this.instance_view.as_ref() != Some(&view) || this.color_method == ColorMethod::Component;
+    // so can the onion's shell, peeling follows any change down to the core.
+    if view.cutaway.onion && (rebuild || !changes.is_empty()) {
+        this.surface_depths = this.cutaway.surface_depths(&renderer);
+        rebuild = true;
+    }
+
+    let mut culled = std::mem::take(&mut this.culled);
+    let mut culled_count = this.culled_count;
+    let mut update_cell = |idx: usize| {
+        let (instance, is_culled) = this.cell_instance(rule.states, &renderer, idx);
+        if culled[idx] != is_culled {
+            culled[idx] = is_culled;
+            if is_culled {
+                culled_count += 1;
+            } else {
+                culled_count -= 1;
+            }
+        }
+        instances.set(idx, instance);
+    };
+    if rebuild {
+        (0..renderer.cell_count()).for_each(&mut update_cell);
+    } else {
+        for idx in changes {
+            update_cell(idx);
+            // whether a cell is enclosed depends on its six neighbors.
+            if view.cull_hidden {
+                let pos = utils::idx_to_pos(idx, bounds);
+                for dir in VONNEUMANN_NEIGHBORS.iter() {
+                    if utils::in_bounds(pos + *dir, bounds) {
+                        update_cell(utils::pos_to_idx(pos + *dir, bounds));
+                    }
+                }
+            }
         }
     }
 
+    this.culled = culled;
+    this.culled_count = culled_count;
+    this.instance_counts = (instances.len(), culled_count);
+    this.instance_view = Some(view);
     this.bounds = bounds;
     this.active_sim = active_sim;
+    this.generation += generations;
+    if generations > 0 || this.history.len() == 0 {
+        let generation = this.generation;
+        this.history.record(generation, &renderer);
+    }
+    if generations > 0 || this.stats.latest().is_none() {
+        let generation = this.generation;
+        this.stats.record(generation, rule.states, &renderer);
+    }
+    if generations > 0 {
+        let generation = this.generation;
+        this.periodicity.record(generation, &renderer);
+    }
     this.update_dt = update_dt;
     this.renderer = Some(renderer);
     this.rule = Some(rule);
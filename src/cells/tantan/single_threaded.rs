@@ -12,6 +12,9 @@ pub struct CellsSingleThreaded {
     neighbors: HashMap<IVec3, u8>,
     changes: HashMap<IVec3, i32>,
     spawn: Vec<(IVec3, u8)>,
+    // cells set since the last `render_changes`, all of them while `redraw`.
+    touched: Vec<IVec3>,
+    redraw: bool,
 }
 
 impl CellsSingleThreaded {
@@ -22,6 +25,8 @@ impl CellsSingleThreaded {
             neighbors: HashMap::new(),
             changes: HashMap::new(),
             spawn: Vec::new(),
+            touched: Vec::new(),
+            redraw: true,
         }
     }
 
@@ -42,12 +47,45 @@ impl Sim for CellsSingleThreaded {
         }
     }
 
+    fn render_changes(&mut self, renderer: &mut CellRenderer, full: bool) {
+        if full || self.redraw {
+            self.render(renderer);
+        } else {
+            for pos in self.touched.iter() {
+                let (value, neighbors) = self
+                    .states
+                    .get(pos)
+                    .map_or((0, 0), |cell| (cell.value, cell.neighbors));
+                renderer.set_pos(*pos, value, neighbors);
+            }
+        }
+        self.touched.clear();
+        self.redraw = false;
+    }
+
     fn spawn_noise(&mut self, rule: &Rule) {
-        utils::make_some_noise_default(utils::center(self.bounding_size), |pos| {
-            self.states.insert(pos, CellState::new(rule.states, 0));
+        let bounds = self.bounding_size;
+        utils::make_some_noise_default(utils::center(bounds), |pos| {
+            if utils::in_bounds(pos, bounds) {
+                self.states.insert(pos, CellState::new(rule.states, 0));
+                self.touched.push(pos);
+            }
         })
     }
 
+    fn set_cell(&mut self, _rule: &Rule, pos: IVec3, value: u8) {
+        self.touched.push(pos);
+        if value == 0 {
+            self.states.remove(&pos);
+        } else {
+            self.states.insert(pos, CellState::new(value, 0));
+        }
+    }
+
+    fn get_cell(&self, pos: IVec3) -> u8 {
+        self.states.get(&pos).map_or(0, |cell| cell.value)
+    }
+
     fn cell_count(&self) -> usize {
         self.states.len()
     }
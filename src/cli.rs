@@ -0,0 +1,22 @@
+// argument helpers shared by the command line subcommands, which panic with
+// a usage message on bad input.
+
+pub(crate) fn parse_value<T: std::str::FromStr>(arg: &str, value: Option<&String>) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| panic!("`{}` expects a number", arg))
+}
+
+pub(crate) fn parse_list<T: std::str::FromStr>(arg: &str, value: Option<&String>) -> Vec<T> {
+    let value = value.unwrap_or_else(|| panic!("`{}` expects a comma separated list", arg));
+    value
+        .split(',')
+        .map(|item| parse_value(arg, Some(&item.to_string())))
+        .collect()
+}
+
+pub(crate) fn parse_path(arg: &str, value: Option<&String>) -> String {
+    value
+        .cloned()
+        .unwrap_or_else(|| panic!("`{}` expects a path", arg))
+}
@@ -8,9 +8,11 @@ pub enum ColorMethod {
     StateLerp,
     DistToCenter,
     Neighbor,
+    Component,
 }
 
 impl ColorMethod {
+    #[allow(clippy::too_many_arguments)]
     pub fn color(
         &self,
         c1: Color,
@@ -19,6 +21,7 @@ impl ColorMethod {
         state: u8,
         neigbors: u8,
         dist_to_center: f32,
+        component: u32,
     ) -> Color {
         match self {
             ColorMethod::Single => c1,
@@ -33,6 +36,14 @@ impl ColorMethod {
                 let dt = neigbors as f32 / 26.0;
                 utils::lerp(c1, c2, dt)
             }
+            ColorMethod::Component => {
+                if component == 0 {
+                    return c1;
+                }
+                // golden angle steps keep neighboring labels far apart in hue.
+                let hue = (component as f32 * 137.508) % 360.0;
+                Color::hsl(hue, 0.8, 0.55)
+            }
         }
     }
 }
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use crate::{cell_renderer::CellRenderer, neighbors::NeighborMethod, utils};
+
+// connected structures of non-dead cells. two cells are connected when they
+// are neighbors under the rule's neighbor method, wrapping at the bounds
+// like the simulators do.
+pub struct Components {
+    // component of each cell, 0 for dead cells and 1.. for the components.
+    pub labels: Vec<u32>,
+    // size of each component, indexed by label - 1.
+    pub sizes: Vec<usize>,
+}
+
+impl Components {
+    pub fn new() -> Self {
+        Self {
+            labels: vec![],
+            sizes: vec![],
+        }
+    }
+
+    pub fn label(&mut self, renderer: &CellRenderer, method: NeighborMethod) {
+        let bounds = renderer.bounds;
+        self.labels.clear();
+        self.labels.resize(renderer.cell_count(), 0);
+        self.sizes.clear();
+
+        let mut stack = vec![];
+        for start in 0..renderer.cell_count() {
+            if renderer.values[start] == 0 || self.labels[start] != 0 {
+                continue;
+            }
+
+            let label = self.sizes.len() as u32 + 1;
+            let mut size = 0;
+            self.labels[start] = label;
+            stack.push(start);
+            while let Some(idx) = stack.pop() {
+                size += 1;
+                let pos = utils::idx_to_pos(idx, bounds);
+                for dir in method.get_neighbor_iter() {
+                    let neighbor = utils::pos_to_idx(utils::wrap(pos + *dir, bounds), bounds);
+                    if renderer.values[neighbor] != 0 && self.labels[neighbor] == 0 {
+                        self.labels[neighbor] = label;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            self.sizes.push(size);
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn largest(&self) -> usize {
+        self.sizes.iter().copied().max().unwrap_or(0)
+    }
+
+    // (size, number of components of that size), smallest first.
+    pub fn size_distribution(&self) -> Vec<(usize, usize)> {
+        let mut distribution = BTreeMap::new();
+        for size in self.sizes.iter() {
+            *distribution.entry(*size).or_insert(0) += 1;
+        }
+        distribution.into_iter().collect()
+    }
+}
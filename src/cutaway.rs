@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::{cell_renderer::CellRenderer, neighbors::VONNEUMANN_NEIGHBORS, utils};
+
+// hides part of the world while drawing so the inside of dense structures
+// can be seen. only the instances are affected, never the simulation.
+#[derive(Clone, PartialEq)]
+pub struct Cutaway {
+    // one clip plane per axis, cells past `clip` are hidden, or the ones
+    // before it when inverted.
+    pub clip_enabled: [bool; 3],
+    pub clip: [i32; 3],
+    pub clip_inverted: [bool; 3],
+    // peels `depth` layers off the surface of the structures and only draws
+    // the next `thickness` layers, see `surface_depths`.
+    pub onion: bool,
+    pub depth: u8,
+    pub thickness: u8,
+}
+
+impl Cutaway {
+    pub fn new() -> Self {
+        Self {
+            clip_enabled: [false; 3],
+            clip: [0; 3],
+            clip_inverted: [false; 3],
+            onion: false,
+            depth: 2,
+            thickness: 2,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.onion || self.clip_enabled.iter().any(|enabled| *enabled)
+    }
+
+    // `surface_depth` is the cell's entry from `surface_depths`.
+    pub fn visible(&self, pos: IVec3, surface_depth: u8) -> bool {
+        for axis in 0..3 {
+            if self.clip_enabled[axis] && (pos[axis] > self.clip[axis]) != self.clip_inverted[axis]
+            {
+                return false;
+            }
+        }
+
+        if self.onion
+            && (surface_depth <= self.depth
+                || surface_depth > self.depth.saturating_add(self.thickness))
+        {
+            return false;
+        }
+        true
+    }
+
+    // how many erosion steps it takes to reach each live cell from a dead or
+    // out of bounds one: the surface is at 1, the layer below it at 2 and so
+    // on. dead cells are 0 and cells deeper than the onion shows share the
+    // depth right below it.
+    pub fn surface_depths(&self, renderer: &CellRenderer) -> Vec<u8> {
+        let bounds = renderer.bounds;
+        let deepest = self.depth.saturating_add(self.thickness).saturating_add(1);
+        let alive = |pos: IVec3| {
+            utils::in_bounds(pos, bounds) && renderer.values[utils::pos_to_idx(pos, bounds)] != 0
+        };
+
+        let mut depths: Vec<u8> = renderer
+            .values
+            .iter()
+            .map(|value| if *value == 0 { 0 } else { deepest })
+            .collect();
+        let mut layer: Vec<usize> = (0..depths.len())
+            .filter(|idx| depths[*idx] != 0)
+            .filter(|idx| {
+                let pos = utils::idx_to_pos(*idx, bounds);
+                VONNEUMANN_NEIGHBORS.iter().any(|dir| !alive(pos + *dir))
+            })
+            .collect();
+
+        let mut depth = 1;
+        while !layer.is_empty() && depth < deepest {
+            for idx in layer.iter() {
+                depths[*idx] = depth;
+            }
+            let mut next = vec![];
+            for idx in layer.iter() {
+                let pos = utils::idx_to_pos(*idx, bounds);
+                for dir in VONNEUMANN_NEIGHBORS.iter() {
+                    let neighbor = pos + *dir;
+                    if !alive(neighbor) {
+                        continue;
+                    }
+                    let neighbor = utils::pos_to_idx(neighbor, bounds);
+                    if depths[neighbor] == deepest {
+                        // marked right away so no other cell queues it again.
+                        depths[neighbor] = depth + 1;
+                        next.push(neighbor);
+                    }
+                }
+            }
+            layer = next;
+            depth += 1;
+        }
+        depths
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, bounds: i32) {
+        for (axis, name) in ["x", "y", "z"].iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.clip_enabled[axis], format!("clip {}", name));
+                ui.add_enabled(
+                    self.clip_enabled[axis],
+                    egui::Slider::new(&mut self.clip[axis], 0..=bounds - 1),
+                );
+                ui.add_enabled(
+                    self.clip_enabled[axis],
+                    egui::Checkbox::new(&mut self.clip_inverted[axis], "invert"),
+                );
+            });
+        }
+
+        ui.checkbox(&mut self.onion, "onion shell")
+            .on_hover_text("peels layers off the surface of the structures");
+        ui.add_enabled(
+            self.onion,
+            egui::Slider::new(&mut self.depth, 0..=(bounds / 2).clamp(0, 64) as u8)
+                .text("peeled layers"),
+        );
+        ui.add_enabled(
+            self.onion,
+            egui::Slider::new(&mut self.thickness, 1..=16).text("shell thickness"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surface_depths_peel_a_block() {
+        let mut renderer = CellRenderer::new();
+        renderer.set_bounds(8);
+        for z in 1..6 {
+            for y in 1..6 {
+                for x in 1..6 {
+                    renderer.set(utils::pos_to_idx(IVec3::new(x, y, z), 8), 1, 0);
+                }
+            }
+        }
+
+        let cutaway = Cutaway::new();
+        let depths = cutaway.surface_depths(&renderer);
+        let depth = |x, y, z| depths[utils::pos_to_idx(IVec3::new(x, y, z), 8)];
+        assert_eq!(depth(0, 0, 0), 0);
+        assert_eq!(depth(1, 3, 3), 1);
+        assert_eq!(depth(2, 3, 3), 2);
+        assert_eq!(depth(3, 3, 3), 3);
+
+        let onion = Cutaway {
+            onion: true,
+            ..Cutaway::new()
+        };
+        assert!(!onion.visible(IVec3::new(2, 3, 3), depth(2, 3, 3)));
+        assert!(onion.visible(IVec3::new(3, 3, 3), depth(3, 3, 3)));
+    }
+}
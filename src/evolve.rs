@@ -0,0 +1,294 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use bevy::tasks::{TaskPool, TaskPoolBuilder};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    cli::{parse_path, parse_value},
+    components::Components,
+    explorer::{random_rule, run_headless, Run},
+    periodicity::Periodicity,
+    rule::{Rule, Value},
+    symmetry,
+};
+
+// scores a headless run, higher is better.
+pub trait Fitness: Send + Sync {
+    fn name(&self) -> String;
+    fn score(&self, rule: &Rule, run: &Run) -> f32;
+}
+
+// size of the largest connected structure, once the world stopped changing.
+pub struct LargestStable;
+
+impl Fitness for LargestStable {
+    fn name(&self) -> String {
+        "largest stable structure".into()
+    }
+
+    fn score(&self, rule: &Rule, run: &Run) -> f32 {
+        if run.periodicity != Some(Periodicity::StillLife) {
+            return 0.0;
+        }
+        let mut components = Components::new();
+        components.label(&run.last, rule.neighbor_method);
+        components.largest() as f32
+    }
+}
+
+// rules that take long to settle into an oscillator, with longer periods
+// breaking ties.
+pub struct LongestLivedOscillator;
+
+impl Fitness for LongestLivedOscillator {
+    fn name(&self) -> String {
+        "longest-lived oscillator".into()
+    }
+
+    fn score(&self, _rule: &Rule, run: &Run) -> f32 {
+        let period = match run.periodicity {
+            Some(Periodicity::Oscillator { period }) => period,
+            Some(Periodicity::Spaceship { period, .. }) => period,
+            _ => return 0.0,
+        };
+        run.settled_at.unwrap_or(0) as f32 + period as f32 / (period as f32 + 1.0)
+    }
+}
+
+pub struct Symmetry;
+
+impl Fitness for Symmetry {
+    fn name(&self) -> String {
+        "symmetry".into()
+    }
+
+    fn score(&self, _rule: &Rule, run: &Run) -> f32 {
+        symmetry::mirror_symmetry(&symmetry::live_cells(&run.last))
+    }
+}
+
+// closeness of the final share of live cells to `target`.
+pub struct Density {
+    pub target: f32,
+}
+
+impl Fitness for Density {
+    fn name(&self) -> String {
+        format!("density near {}", self.target)
+    }
+
+    fn score(&self, _rule: &Rule, run: &Run) -> f32 {
+        let population = run.populations.last().copied().unwrap_or(0);
+        if population == 0 {
+            return 0.0;
+        }
+        let density = population as f32 / run.volume().max(1) as f32;
+        1.0 - (density - self.target).abs()
+    }
+}
+
+pub fn parse_fitness(name: &str) -> Result<Box<dyn Fitness>, String> {
+    match name.split_once('=') {
+        Some(("density", target)) => match target.parse() {
+            Ok(target) => Ok(Box::new(Density { target })),
+            Err(_) => Err(format!("invalid density `{}`", target)),
+        },
+        None if name == "stable" => Ok(Box::new(LargestStable)),
+        None if name == "oscillator" => Ok(Box::new(LongestLivedOscillator)),
+        None if name == "symmetry" => Ok(Box::new(Symmetry)),
+        _ => Err(format!(
+            "unknown fitness `{}`, expected stable, oscillator, symmetry or density=X",
+            name
+        )),
+    }
+}
+
+pub fn mutate(rule: &mut Rule, rng: &mut impl Rng) {
+    let max_neighbors = rule.neighbor_method.get_neighbor_iter().len() as u8;
+    match rng.gen_range(0..3) {
+        0 => {
+            let idx = rng.gen_range(0..=max_neighbors);
+            let enabled = rule.survival_rule.in_range(idx);
+            rule.survival_rule.set(idx, !enabled);
+        }
+        1 => {
+            // birth on 0 neighbors is left out, see `random_rule`.
+            let idx = rng.gen_range(1..=max_neighbors);
+            let enabled = rule.birth_rule.in_range(idx);
+            rule.birth_rule.set(idx, !enabled);
+        }
+        _ => {
+            rule.states = if rng.gen_bool(0.5) {
+                rule.states.saturating_add(1)
+            } else {
+                rule.states.saturating_sub(1).max(1)
+            };
+        }
+    }
+}
+
+// takes each neighbor count, the state count and the neighbor method from
+// either parent.
+pub fn crossover(a: &Rule, b: &Rule, rng: &mut impl Rng) -> Rule {
+    let mut pick = |a: &Value, b: &Value| {
+        let mut result = Value::new(&[]);
+        for idx in 0..27 {
+            let parent = if rng.gen_bool(0.5) { a } else { b };
+            result.set(idx, parent.in_range(idx));
+        }
+        result
+    };
+    let survival_rule = pick(&a.survival_rule, &b.survival_rule);
+    let birth_rule = pick(&a.birth_rule, &b.birth_rule);
+
+    let mut rule = Rule {
+        survival_rule,
+        birth_rule,
+        states: if rng.gen_bool(0.5) {
+            a.states
+        } else {
+            b.states
+        },
+        neighbor_method: if rng.gen_bool(0.5) {
+            a.neighbor_method
+        } else {
+            b.neighbor_method
+        },
+    };
+    // von Neumann rules have no use for counts above 6.
+    let max_neighbors = rule.neighbor_method.get_neighbor_iter().len() as u8;
+    for idx in max_neighbors + 1..27 {
+        rule.survival_rule.set(idx, false);
+        rule.birth_rule.set(idx, false);
+    }
+    rule.birth_rule.set(0, false);
+    rule
+}
+
+pub struct EvolveOptions {
+    pub population: usize,
+    pub generations: usize,
+    pub ticks: usize,
+    pub bounds: i32,
+    pub threads: usize,
+    pub output: String,
+}
+
+impl Default for EvolveOptions {
+    fn default() -> Self {
+        Self {
+            population: 32,
+            generations: 20,
+            ticks: 100,
+            bounds: 32,
+            threads: num_cpus::get(),
+            output: "rules.txt".into(),
+        }
+    }
+}
+
+fn evaluate(
+    rules: &[Rule],
+    options: &EvolveOptions,
+    fitness: &dyn Fitness,
+    task_pool: &TaskPool,
+) -> Vec<(Rule, f32)> {
+    task_pool.scope(|s| {
+        for rule in rules.iter() {
+            s.spawn(async move {
+                let run = run_headless(rule, options.bounds, options.ticks, task_pool);
+                (rule.clone(), fitness.score(rule, &run))
+            });
+        }
+    })
+}
+
+// keeps the best quarter of each generation and refills the rest with
+// mutated children of it. returns every scored rule, best first.
+pub fn evolve(
+    options: &EvolveOptions,
+    fitness: &dyn Fitness,
+    task_pool: &TaskPool,
+    mut on_generation: impl FnMut(usize, &[(Rule, f32)]),
+) -> Vec<(Rule, f32)> {
+    let mut rng = rand::thread_rng();
+    let mut rules: Vec<Rule> = (0..options.population)
+        .map(|_| random_rule(&mut rng))
+        .collect();
+
+    let mut scored: Vec<(Rule, f32)> = vec![];
+    for generation in 0..options.generations {
+        scored.extend(evaluate(&rules, options, fitness, task_pool));
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut seen = HashSet::new();
+        scored.retain(|(rule, _)| seen.insert(rule.to_string()));
+        on_generation(generation, &scored);
+
+        let elite = &scored[..(options.population / 4).max(1).min(scored.len())];
+        rules = (0..options.population)
+            .map(|_| {
+                let a = &elite.choose(&mut rng).expect("choosing parent").0;
+                let b = &elite.choose(&mut rng).expect("choosing parent").0;
+                let mut child = crossover(a, b, &mut rng);
+                mutate(&mut child, &mut rng);
+                child
+            })
+            .collect();
+    }
+    scored
+}
+
+fn write_ranking(path: &str, fitness: &dyn Fitness, ranking: &[(Rule, f32)]) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "# fitness: {}", fitness.name())?;
+    for (rule, score) in ranking.iter() {
+        writeln!(w, "{:.4} {}", score, rule)?;
+    }
+    w.flush()
+}
+
+pub fn run(args: &[String]) {
+    let mut options = EvolveOptions::default();
+    let mut fitness: Box<dyn Fitness> = Box::new(LargestStable);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fitness" => {
+                let name = args.next().map(String::as_str).unwrap_or_default();
+                fitness = parse_fitness(name).unwrap_or_else(|err| panic!("{}", err));
+            }
+            "--population" => options.population = parse_value(arg, args.next()),
+            "--generations" => options.generations = parse_value(arg, args.next()),
+            "--ticks" => options.ticks = parse_value(arg, args.next()),
+            "--bounds" => options.bounds = parse_value(arg, args.next()),
+            "--threads" => options.threads = parse_value(arg, args.next()),
+            "--output" => options.output = parse_path(arg, args.next()),
+            _ => panic!(
+                "unknown argument `{}`, expected --fitness F, --population N, --generations N, \
+                 --ticks N, --bounds N, --threads N or --output PATH",
+                arg
+            ),
+        }
+    }
+
+    let task_pool = TaskPoolBuilder::new().num_threads(options.threads).build();
+    let ranking = evolve(
+        &options,
+        fitness.as_ref(),
+        &task_pool,
+        |generation, scored| {
+            if let Some((rule, score)) = scored.first() {
+                println!("generation {}: best {:.4} {}", generation, score, rule);
+            }
+        },
+    );
+
+    match write_ranking(&options.output, fitness.as_ref(), &ranking) {
+        Ok(()) => println!("wrote {} rules to {}", ranking.len(), options.output),
+        Err(err) => eprintln!("writing {} failed: {}", options.output, err),
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::{
+    cells::Example,
+    color_method::ColorMethod,
+    neighbors::NeighborMethod,
+    rule::{Rule, Value},
+};
+
+pub fn default_examples() -> Vec<Example> {
+    vec![
+        Example {
+            name: "builder".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[2, 6, 9]),
+                birth_rule: Value::new(&[4, 6, 8, 9, 10]),
+                states: 10,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::DistToCenter,
+            color1: Color::YELLOW,
+            color2: Color::RED,
+        },
+        Example {
+            name: "VN pyramid".into(),
+            rule: Rule {
+                survival_rule: Value::from_range(0..=6),
+                birth_rule: Value::new(&[1,3]),
+                states: 2,
+                neighbor_method: NeighborMethod::VonNeumann,
+            },
+            color_method: ColorMethod::DistToCenter,
+            color1: Color::GREEN,
+            color2: Color::BLUE,
+        },
+        Example {
+            name: "fancy snancy".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[0,1,2,3,7,8,9,11,13,18,21,22,24,26]),
+                birth_rule: Value::new(&[4,13,17,20,21,22,23,24,26]),
+                states: 4,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::StateLerp,
+            color1: Color::RED,
+            color2: Color::BLUE,
+        },
+        Example {
+            name: "pretty crystals".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[5,6,7,8]),
+                birth_rule: Value::new(&[6,7,9]),
+                states: 10,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::DistToCenter,
+            color1: Color::GREEN,
+            color2: Color::BLUE,
+        },
+        Example {
+            name: "swapping structures".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[3,6,9]),
+                birth_rule: Value::new(&[4,8,10]),
+                states: 20,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::StateLerp,
+            color1: Color::RED,
+            color2: Color::GREEN,
+        },
+        Example {
+            name: "slowly expanding blob".into(),
+            rule: Rule {
+                survival_rule: Value::from_range(9..=26),
+                birth_rule: Value::new(&[5,6,7,12,13,15]),
+                states: 20,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::StateLerp,
+            color1: Color::YELLOW,
+            color2: Color::BLUE,
+        },
+        Example {
+            name: "445".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[4]),
+                birth_rule: Value::new(&[4]),
+                states: 5,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::StateLerp,
+            color1: Color::BLACK,
+            color2: Color::RED,
+        },
+        Example {
+            name: "expand then die".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[4]),
+                birth_rule: Value::new(&[3]),
+                states: 20,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::StateLerp,
+            color1: Color::BLACK,
+            color2: Color::RED,
+        },
+        Example {
+            name: "no idea what to call this".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[6,7]),
+                birth_rule: Value::new(&[4,6,9,10,11]),
+                states: 6,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::StateLerp,
+            color1: Color::BLUE,
+            color2: Color::RED,
+        },
+        Example {
+            name: "LARGE LINES".into(),
+            rule: Rule {
+                survival_rule: Value::new(&[5]),
+                birth_rule: Value::new(&[4, 6, 9, 10, 11, 16, 17, 18, 19, 20, 21, 22, 23, 24]),
+                states: 35,
+                neighbor_method: NeighborMethod::Moore,
+            },
+            color_method: ColorMethod::StateLerp,
+            color1: Color::BLUE,
+            color2: Color::RED,
+        },
+    ]
+}
@@ -0,0 +1,276 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, TaskPool},
+};
+use bevy_egui::egui;
+use futures_lite::future;
+use rand::Rng;
+
+use crate::{
+    cell_renderer::CellRenderer,
+    cells::{chunked::CellsChunked, Example, Sim},
+    color_method::ColorMethod,
+    neighbors::NeighborMethod,
+    periodicity::{PeriodDetector, Periodicity},
+    rule::{Rule, Value},
+};
+
+pub fn random_rule(rng: &mut impl Rng) -> Rule {
+    let neighbor_method = if rng.gen_bool(0.5) {
+        NeighborMethod::Moore
+    } else {
+        NeighborMethod::VonNeumann
+    };
+    let max_neighbors = neighbor_method.get_neighbor_iter().len() as u8;
+
+    let survival_odds = rng.gen_range(0.1..0.5);
+    let survival: Vec<u8> = (0..=max_neighbors)
+        .filter(|_| rng.gen_bool(survival_odds))
+        .collect();
+
+    // birth on 0 neighbors fills the world in one generation.
+    let birth_odds = rng.gen_range(0.05..0.3);
+    let mut birth: Vec<u8> = (1..=max_neighbors)
+        .filter(|_| rng.gen_bool(birth_odds))
+        .collect();
+    if birth.is_empty() {
+        birth.push(rng.gen_range(1..=max_neighbors));
+    }
+
+    Rule {
+        survival_rule: Value::new(&survival),
+        birth_rule: Value::new(&birth),
+        states: rng.gen_range(1..=20),
+        neighbor_method,
+    }
+}
+
+// what happened to the world over a headless run.
+pub struct Run {
+    pub populations: Vec<usize>,
+    // cells that changed value in each generation.
+    pub changes: Vec<usize>,
+    pub periodicity: Option<Periodicity>,
+    // generation the periodicity was first noticed.
+    pub settled_at: Option<usize>,
+    pub last: CellRenderer,
+}
+
+impl Run {
+    pub fn volume(&self) -> usize {
+        self.last.cell_count()
+    }
+}
+
+// runs `rule` from the usual noise seed without rendering anything.
+pub fn run_headless(rule: &Rule, bounds: i32, generations: usize, task_pool: &TaskPool) -> Run {
+    let mut sim = CellsChunked::new();
+    let bounds = sim.set_bounds(bounds);
+    sim.spawn_noise(rule);
+
+    let mut renderer = CellRenderer::new();
+    renderer.set_bounds(bounds);
+    sim.render(&mut renderer);
+    let mut previous = renderer.values.clone();
+
+    let mut detector = PeriodDetector::new(generations + 1);
+    let mut run = Run {
+        populations: vec![],
+        changes: vec![],
+        periodicity: None,
+        settled_at: None,
+        last: CellRenderer::new(),
+    };
+    for generation in 1..=generations {
+        sim.update(rule, task_pool);
+        sim.render(&mut renderer);
+
+        let changes = previous
+            .iter()
+            .zip(renderer.values.iter())
+            .filter(|(old, new)| old != new)
+            .count();
+        run.populations
+            .push(renderer.values.iter().filter(|value| **value != 0).count());
+        run.changes.push(changes);
+        previous.copy_from_slice(&renderer.values);
+
+        detector.record(generation as u64, &renderer);
+        run.periodicity = detector.result();
+        if run.periodicity.is_some() {
+            run.settled_at.get_or_insert(generation);
+        }
+        if run.periodicity == Some(Periodicity::Dead) {
+            break;
+        }
+    }
+
+    run.last = renderer;
+    run
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Behavior {
+    DiesOut,
+    Explodes,
+    Stable,
+    Oscillating,
+    Chaotic,
+}
+
+impl Behavior {
+    pub fn classify(run: &Run) -> Self {
+        let volume = run.volume().max(1) as f32;
+        let population = run.populations.last().copied().unwrap_or(0);
+        if population == 0 {
+            return Behavior::DiesOut;
+        }
+
+        // judge by the last quarter, after the noise had time to settle.
+        let tail = (run.populations.len() / 4).max(1);
+        let start = run.populations.len() - tail;
+        let peak = run.populations[start..].iter().copied().max().unwrap_or(0);
+        if peak as f32 / volume > 0.25 {
+            return Behavior::Explodes;
+        }
+
+        match run.periodicity {
+            Some(Periodicity::Dead) => return Behavior::DiesOut,
+            Some(Periodicity::StillLife) => return Behavior::Stable,
+            Some(_) => return Behavior::Oscillating,
+            None => {}
+        }
+
+        let changes: usize = run.changes[start..].iter().sum();
+        let population: usize = run.populations[start..].iter().sum();
+        if (changes as f32 / population.max(1) as f32) < 0.01 {
+            Behavior::Stable
+        } else {
+            Behavior::Chaotic
+        }
+    }
+
+    pub fn is_interesting(&self) -> bool {
+        matches!(
+            self,
+            Behavior::Stable | Behavior::Oscillating | Behavior::Chaotic
+        )
+    }
+}
+
+impl fmt::Display for Behavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Behavior::DiesOut => "dies out",
+            Behavior::Explodes => "explodes",
+            Behavior::Stable => "stable",
+            Behavior::Oscillating => "oscillating",
+            Behavior::Chaotic => "chaotic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// tries random rules in the background, the interesting ones end up as
+// examples.
+pub struct Explorer {
+    candidates: usize,
+    generations: usize,
+    bounds: i32,
+    task: Option<Task<Vec<(Rule, Behavior)>>>,
+    progress: Arc<AtomicUsize>,
+    results: Vec<(Rule, Behavior)>,
+}
+
+impl Explorer {
+    pub fn new() -> Self {
+        Self {
+            candidates: 32,
+            generations: 100,
+            bounds: 32,
+            task: None,
+            progress: Arc::new(AtomicUsize::new(0)),
+            results: vec![],
+        }
+    }
+
+    fn start(&mut self) {
+        let candidates = self.candidates;
+        let generations = self.generations;
+        let bounds = self.bounds;
+        let progress = self.progress.clone();
+        progress.store(0, Ordering::Relaxed);
+
+        self.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            let mut rng = rand::thread_rng();
+            let mut results = vec![];
+            for _ in 0..candidates {
+                let rule = random_rule(&mut rng);
+                let run = run_headless(&rule, bounds, generations, AsyncComputeTaskPool::get());
+                results.push((rule, Behavior::classify(&run)));
+                progress.fetch_add(1, Ordering::Relaxed);
+            }
+            results
+        }));
+    }
+
+    // returns the interesting rules of a finished exploration.
+    pub fn poll(&mut self) -> Vec<Example> {
+        let results = match &mut self.task {
+            Some(task) => match future::block_on(future::poll_once(task)) {
+                Some(results) => results,
+                None => return vec![],
+            },
+            None => return vec![],
+        };
+        self.task = None;
+
+        let examples = results
+            .iter()
+            .filter(|(_, behavior)| behavior.is_interesting())
+            .map(|(rule, behavior)| Example {
+                name: format!("{} {}", behavior, rule),
+                rule: rule.clone(),
+                color_method: ColorMethod::StateLerp,
+                color1: Color::YELLOW,
+                color2: Color::RED,
+            })
+            .collect();
+        self.results = results;
+        examples
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let running = self.task.is_some();
+        ui.add_enabled_ui(!running, |ui| {
+            ui.add(egui::Slider::new(&mut self.candidates, 1..=256).text("rules"));
+            ui.add(egui::Slider::new(&mut self.generations, 10..=1000).text("generations"));
+            ui.add(egui::Slider::new(&mut self.bounds, 16..=64).text("bounds"));
+            if ui.button("explore").clicked() {
+                self.start();
+            }
+        });
+
+        if running {
+            ui.label(format!(
+                "exploring: {} / {}",
+                self.progress.load(Ordering::Relaxed),
+                self.candidates
+            ));
+        } else if !self.results.is_empty() {
+            ui.collapsing(format!("last results ({})", self.results.len()), |ui| {
+                for (rule, behavior) in self.results.iter() {
+                    ui.label(format!("{}: {}", rule, behavior));
+                }
+            });
+        }
+    }
+}
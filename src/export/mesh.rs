@@ -0,0 +1,125 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use bevy::prelude::*;
+
+use crate::utils;
+
+// face normal and the two edge directions spanning it, with u x v = normal.
+const FACES: [(IVec3, Vec3, Vec3); 6] = [
+    (IVec3::X, Vec3::Y, Vec3::Z),
+    (IVec3::NEG_X, Vec3::Z, Vec3::Y),
+    (IVec3::Y, Vec3::Z, Vec3::X),
+    (IVec3::NEG_Y, Vec3::X, Vec3::Z),
+    (IVec3::Z, Vec3::X, Vec3::Y),
+    (IVec3::NEG_Z, Vec3::Y, Vec3::X),
+];
+
+pub struct CellMesh {
+    positions: Vec<Vec3>,
+    colors: Vec<[u8; 3]>,
+    triangles: Vec<([u32; 3], Vec3)>,
+}
+
+impl CellMesh {
+    // unit cubes centered like the rendered instances, keeping only the faces
+    // between a live and a dead cell.
+    pub fn new(bounds: i32, cells: &[(IVec3, Color)]) -> Self {
+        let live: HashSet<IVec3> = cells.iter().map(|(pos, _)| *pos).collect();
+        let center = utils::center(bounds);
+
+        let mut mesh = Self {
+            positions: vec![],
+            colors: vec![],
+            triangles: vec![],
+        };
+
+        for (pos, color) in cells {
+            let [r, g, b, _] = color.as_rgba_f32();
+            let rgb = [r, g, b].map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+            let cube_center = (*pos - center).as_vec3();
+
+            for (normal, u, v) in FACES {
+                if live.contains(&(*pos + normal)) {
+                    continue;
+                }
+
+                let first = mesh.positions.len() as u32;
+                let face_center = cube_center + normal.as_vec3() * 0.5;
+                for (du, dv) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+                    mesh.positions.push(face_center + u * du + v * dv);
+                    mesh.colors.push(rgb);
+                }
+
+                let normal = normal.as_vec3();
+                mesh.triangles.push(([first, first + 1, first + 2], normal));
+                mesh.triangles.push(([first, first + 2, first + 3], normal));
+            }
+        }
+        mesh
+    }
+
+    pub fn write_obj(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "# rs-automata cells, vertex colors follow each position")?;
+        for (pos, [r, g, b]) in self.positions.iter().zip(self.colors.iter()) {
+            writeln!(
+                w,
+                "v {} {} {} {:.4} {:.4} {:.4}",
+                pos.x,
+                pos.y,
+                pos.z,
+                *r as f32 / 255.0,
+                *g as f32 / 255.0,
+                *b as f32 / 255.0
+            )?;
+        }
+        for ([a, b, c], _) in self.triangles.iter() {
+            writeln!(w, "f {} {} {}", a + 1, b + 1, c + 1)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_ply(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "ply")?;
+        writeln!(w, "format ascii 1.0")?;
+        writeln!(w, "element vertex {}", self.positions.len())?;
+        writeln!(w, "property float x")?;
+        writeln!(w, "property float y")?;
+        writeln!(w, "property float z")?;
+        writeln!(w, "property uchar red")?;
+        writeln!(w, "property uchar green")?;
+        writeln!(w, "property uchar blue")?;
+        writeln!(w, "element face {}", self.triangles.len())?;
+        writeln!(w, "property list uchar int vertex_indices")?;
+        writeln!(w, "end_header")?;
+        for (pos, [r, g, b]) in self.positions.iter().zip(self.colors.iter()) {
+            writeln!(w, "{} {} {} {} {} {}", pos.x, pos.y, pos.z, r, g, b)?;
+        }
+        for ([a, b, c], _) in self.triangles.iter() {
+            writeln!(w, "3 {} {} {}", a, b, c)?;
+        }
+        Ok(())
+    }
+
+    // binary stl, one unit per cell. stl has no colors.
+    pub fn write_stl(&self, w: &mut impl Write) -> io::Result<()> {
+        let mut header = [0; 80];
+        let title = b"rs-automata cells";
+        header[..title.len()].copy_from_slice(title);
+        w.write_all(&header)?;
+        w.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+
+        for (triangle, normal) in self.triangles.iter() {
+            let corners = triangle.map(|idx| self.positions[idx as usize]);
+            for vector in std::iter::once(normal).chain(corners.iter()) {
+                for component in vector.to_array() {
+                    w.write_all(&component.to_le_bytes())?;
+                }
+            }
+            w.write_all(&0u16.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
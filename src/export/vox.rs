@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use bevy::prelude::*;
+
+// a single .vox model can't be larger than this along any axis.
+const MAX_MODEL_SIZE: i32 = 256;
+// palette index 0 means empty, leaving 255 usable colors.
+const MAX_PALETTE: usize = 255;
+
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    indices: HashMap<[u8; 3], u8>,
+    mask: u8,
+}
+
+impl Palette {
+    // drops low bits from every channel until the distinct colors fit.
+    fn new(colors: &[[u8; 3]]) -> Self {
+        for dropped_bits in 0..8 {
+            let mask = 0xff << dropped_bits;
+            let mut palette = Self {
+                colors: vec![],
+                indices: HashMap::new(),
+                mask,
+            };
+
+            let mut fits = true;
+            for color in colors {
+                let key = palette.quantize(*color);
+                if palette.indices.contains_key(&key) {
+                    continue;
+                }
+                if palette.colors.len() == MAX_PALETTE {
+                    fits = false;
+                    break;
+                }
+
+                palette.indices.insert(key, palette.colors.len() as u8 + 1);
+                // center the color inside its bucket.
+                let half = !mask >> 1;
+                palette.colors.push(key.map(|channel| channel | half));
+            }
+
+            if fits {
+                return palette;
+            }
+        }
+        unreachable!("1 bit per channel always fits in the palette")
+    }
+
+    fn quantize(&self, color: [u8; 3]) -> [u8; 3] {
+        color.map(|channel| channel & self.mask)
+    }
+
+    fn index(&self, color: [u8; 3]) -> u8 {
+        self.indices[&self.quantize(color)]
+    }
+}
+
+fn to_rgb(color: Color) -> [u8; 3] {
+    let [r, g, b, _] = color.as_rgba_f32();
+    [r, g, b].map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+// .vox is z-up, the world is y-up.
+fn to_vox(pos: IVec3) -> IVec3 {
+    IVec3::new(pos.x, pos.z, pos.y)
+}
+
+fn write_chunk(
+    w: &mut impl Write,
+    id: &[u8; 4],
+    content: &[u8],
+    children: &[u8],
+) -> io::Result<()> {
+    w.write_all(id)?;
+    w.write_all(&(content.len() as u32).to_le_bytes())?;
+    w.write_all(&(children.len() as u32).to_le_bytes())?;
+    w.write_all(content)?;
+    w.write_all(children)
+}
+
+fn push_i32(buffer: &mut Vec<u8>, value: i32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_dict(buffer: &mut Vec<u8>, entries: &[(&str, String)]) {
+    push_i32(buffer, entries.len() as i32);
+    for (key, value) in entries {
+        for string in [*key, value.as_str()] {
+            push_i32(buffer, string.len() as i32);
+            buffer.extend_from_slice(string.as_bytes());
+        }
+    }
+}
+
+struct Model {
+    origin: IVec3,
+    size: IVec3,
+    voxels: Vec<[u8; 4]>,
+}
+
+// writes the cells as a MagicaVoxel file, split into 256³ models that are
+// positioned through the scene graph when the world is bigger than that.
+pub fn write(w: &mut impl Write, bounds: i32, cells: &[(IVec3, Color)]) -> io::Result<()> {
+    let rgb: Vec<[u8; 3]> = cells.iter().map(|(_, color)| to_rgb(*color)).collect();
+    let palette = Palette::new(&rgb);
+
+    let models_per_axis = (bounds + MAX_MODEL_SIZE - 1) / MAX_MODEL_SIZE;
+    let mut models: HashMap<IVec3, Model> = HashMap::new();
+    for ((pos, _), color) in cells.iter().zip(rgb) {
+        let pos = to_vox(*pos);
+        let model_pos = pos / MAX_MODEL_SIZE;
+        let model = models.entry(model_pos).or_insert_with(|| {
+            let origin = model_pos * MAX_MODEL_SIZE;
+            Model {
+                origin,
+                size: (IVec3::splat(bounds) - origin).min(IVec3::splat(MAX_MODEL_SIZE)),
+                voxels: vec![],
+            }
+        });
+
+        let local = pos - model.origin;
+        model.voxels.push([
+            local.x as u8,
+            local.y as u8,
+            local.z as u8,
+            palette.index(color),
+        ]);
+    }
+
+    let mut models: Vec<Model> = models.into_values().collect();
+    models.sort_by_key(|model| {
+        let pos = model.origin / MAX_MODEL_SIZE;
+        pos.x + (pos.y + pos.z * models_per_axis) * models_per_axis
+    });
+    if models.is_empty() {
+        models.push(Model {
+            origin: IVec3::ZERO,
+            size: IVec3::splat(bounds.min(MAX_MODEL_SIZE)),
+            voxels: vec![],
+        });
+    }
+
+    let mut children = vec![];
+    for model in models.iter() {
+        let mut size = vec![];
+        for axis in model.size.to_array() {
+            push_i32(&mut size, axis);
+        }
+        write_chunk(&mut children, b"SIZE", &size, &[])?;
+
+        let mut xyzi = vec![];
+        push_i32(&mut xyzi, model.voxels.len() as i32);
+        for voxel in model.voxels.iter() {
+            xyzi.extend_from_slice(voxel);
+        }
+        write_chunk(&mut children, b"XYZI", &xyzi, &[])?;
+    }
+
+    // root transform -> group -> (transform -> shape) per model.
+    let mut root = vec![];
+    push_i32(&mut root, 0);
+    push_dict(&mut root, &[]);
+    push_i32(&mut root, 1);
+    push_i32(&mut root, -1);
+    push_i32(&mut root, -1);
+    push_i32(&mut root, 1);
+    push_dict(&mut root, &[]);
+    write_chunk(&mut children, b"nTRN", &root, &[])?;
+
+    let mut group = vec![];
+    push_i32(&mut group, 1);
+    push_dict(&mut group, &[]);
+    push_i32(&mut group, models.len() as i32);
+    for i in 0..models.len() {
+        push_i32(&mut group, 2 + 2 * i as i32);
+    }
+    write_chunk(&mut children, b"nGRP", &group, &[])?;
+
+    for (i, model) in models.iter().enumerate() {
+        let node = 2 + 2 * i as i32;
+        // models are placed by their center, keep the world centered on the origin.
+        let center = model.origin + model.size / 2 - IVec3::splat(bounds / 2);
+
+        let mut transform = vec![];
+        push_i32(&mut transform, node);
+        push_dict(&mut transform, &[]);
+        push_i32(&mut transform, node + 1);
+        push_i32(&mut transform, -1);
+        push_i32(&mut transform, 0);
+        push_i32(&mut transform, 1);
+        push_dict(
+            &mut transform,
+            &[("_t", format!("{} {} {}", center.x, center.y, center.z))],
+        );
+        write_chunk(&mut children, b"nTRN", &transform, &[])?;
+
+        let mut shape = vec![];
+        push_i32(&mut shape, node + 1);
+        push_dict(&mut shape, &[]);
+        push_i32(&mut shape, 1);
+        push_i32(&mut shape, i as i32);
+        push_dict(&mut shape, &[]);
+        write_chunk(&mut children, b"nSHP", &shape, &[])?;
+    }
+
+    let mut rgba = vec![0; 256 * 4];
+    for (i, color) in palette.colors.iter().enumerate() {
+        rgba[i * 4..i * 4 + 3].copy_from_slice(color);
+        rgba[i * 4 + 3] = 255;
+    }
+    write_chunk(&mut children, b"RGBA", &rgba, &[])?;
+
+    w.write_all(b"VOX ")?;
+    w.write_all(&150i32.to_le_bytes())?;
+    write_chunk(w, b"MAIN", &[], &children)
+}
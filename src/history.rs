@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{cell_renderer::CellRenderer, utils};
+
+// a generation is stored as the cells that changed since the one before it.
+struct Frame {
+    generation: u64,
+    changes: Vec<(u32, u8, u8)>,
+}
+
+pub struct History {
+    capacity: usize,
+    frames: VecDeque<Frame>,
+    position: usize,
+    bounds: i32,
+    values: Vec<u8>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            frames: VecDeque::new(),
+            position: 0,
+            bounds: 0,
+            values: vec![],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.position = 0;
+        self.values.clear();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        if capacity == 0 {
+            self.clear();
+            return;
+        }
+
+        // the oldest frames can only be dropped while standing on the newest one.
+        if self.frames.len() > capacity {
+            self.seek(self.frames.len() - 1);
+            while self.frames.len() > capacity {
+                self.frames.pop_front();
+            }
+            self.position = self.frames.len() - 1;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn generation(&self) -> Option<u64> {
+        self.frames.get(self.position).map(|frame| frame.generation)
+    }
+
+    pub fn record(&mut self, generation: u64, renderer: &CellRenderer) {
+        if self.capacity == 0 {
+            return;
+        }
+        if renderer.bounds != self.bounds {
+            self.clear();
+            self.bounds = renderer.bounds;
+        }
+        if self.values.len() != renderer.values.len() {
+            self.values.resize(renderer.values.len(), 0);
+        }
+
+        // resuming from a past generation discards everything after it.
+        self.frames.truncate(self.position + 1);
+
+        let changes = self
+            .values
+            .iter_mut()
+            .zip(renderer.values.iter())
+            .enumerate()
+            .filter(|(_, (old, new))| *old != *new)
+            .map(|(idx, (old, new))| {
+                let change = (idx as u32, *old, *new);
+                *old = *new;
+                change
+            })
+            .collect();
+
+        self.frames.push_back(Frame {
+            generation,
+            changes,
+        });
+        while self.frames.len() > self.capacity {
+            self.frames.pop_front();
+        }
+        self.position = self.frames.len() - 1;
+    }
+
+    pub fn seek(&mut self, target: usize) {
+        let target = target.min(self.frames.len().saturating_sub(1));
+        while self.position > target {
+            for (idx, old, _) in self.frames[self.position].changes.iter() {
+                self.values[*idx as usize] = *old;
+            }
+            self.position -= 1;
+        }
+        while self.position < target {
+            self.position += 1;
+            for (idx, _, new) in self.frames[self.position].changes.iter() {
+                self.values[*idx as usize] = *new;
+            }
+        }
+    }
+
+    pub fn cells(&self) -> Vec<(IVec3, u8)> {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(idx, value)| (utils::idx_to_pos(idx, self.bounds), *value))
+            .collect()
+    }
+}
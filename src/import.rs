@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    snapshot,
+    utils::{self, invalid_data},
+};
+
+// loads a .vox model, or a text file of `x y z state` lines, centered in
+// the world and clipped to its bounds.
+pub fn load(path: &Path, bounds: i32, states: u8) -> io::Result<Vec<(IVec3, u8)>> {
+    let cells = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("vox") => read_vox(&fs::read(path)?, states)?,
+        _ => read_text(&fs::read_to_string(path)?, states)?,
+    };
+    Ok(center(cells, bounds))
+}
+
+pub(crate) fn center(cells: Vec<(IVec3, u8)>, bounds: i32) -> Vec<(IVec3, u8)> {
+    let (min, max) = match utils::bounding_box(cells.iter().map(|(pos, _)| *pos)) {
+        Some(bounding_box) => bounding_box,
+        None => return cells,
+    };
+    // cells may lie anywhere in i32, so the middle of the box and every
+    // cell's distance to it are taken in i64.
+    let center = utils::center(bounds);
+    let mid = [0, 1, 2].map(|axis| (min[axis] as i64 + max[axis] as i64) / 2);
+
+    cells
+        .into_iter()
+        .filter_map(|(pos, value)| {
+            let axis = |axis: usize| {
+                i32::try_from(pos[axis] as i64 - mid[axis] + center[axis] as i64).ok()
+            };
+            Some((IVec3::new(axis(0)?, axis(1)?, axis(2)?), value))
+        })
+        .filter(|(pos, _)| utils::in_bounds(*pos, bounds))
+        .collect()
+}
+
+// .vox translations are arbitrary, placing a model with one can overflow.
+fn translate(pos: IVec3, offset: IVec3) -> io::Result<IVec3> {
+    let axis = |axis: usize| {
+        pos[axis]
+            .checked_add(offset[axis])
+            .ok_or_else(|| invalid_data("model translated out of range"))
+    };
+    Ok(IVec3::new(axis(0)?, axis(1)?, axis(2)?))
+}
+
+fn read_text(text: &str, states: u8) -> io::Result<Vec<(IVec3, u8)>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| snapshot::parse_cell(line).map(|(pos, value)| (pos, value.min(states))))
+        .filter(|cell| !matches!(cell, Ok((_, 0))))
+        .collect()
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < count {
+            return Err(invalid_data("unexpected end of .vox file"));
+        }
+        let (bytes, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        let mut bytes = [0; 4];
+        self.bytes(4)?.read_exact(&mut bytes)?;
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    // sizes and counts are signed in the file, negative ones mean corruption.
+    fn len(&mut self) -> io::Result<usize> {
+        usize::try_from(self.i32()?).map_err(|_| invalid_data("negative length in .vox file"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.len()?;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| invalid_data("invalid string"))
+    }
+
+    fn dict(&mut self) -> io::Result<HashMap<String, String>> {
+        let count = self.i32()?;
+        (0..count)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+}
+
+// every voxel becomes a live cell. models are placed by the transform node
+// pointing at their shape, if there is one.
+fn read_vox(bytes: &[u8], states: u8) -> io::Result<Vec<(IVec3, u8)>> {
+    let mut reader = Reader(bytes);
+    if reader.bytes(4)? != b"VOX " {
+        return Err(invalid_data("not a .vox file"));
+    }
+    reader.i32()?;
+
+    let mut sizes = vec![];
+    let mut models: Vec<Vec<IVec3>> = vec![];
+    let mut shape_models: HashMap<i32, i32> = HashMap::new();
+    let mut translations: HashMap<i32, IVec3> = HashMap::new();
+
+    // MAIN only has children, so its chunks can be read as one flat list.
+    while !reader.0.is_empty() {
+        let id = reader.bytes(4)?;
+        let content_len = reader.len()?;
+        let children_len = reader.len()?;
+        if id == b"MAIN" {
+            continue;
+        }
+
+        let mut content = Reader(reader.bytes(content_len)?);
+        reader.bytes(children_len)?;
+        match id {
+            b"SIZE" => sizes.push(IVec3::new(content.i32()?, content.i32()?, content.i32()?)),
+            b"XYZI" => {
+                let count = content.len()?;
+                let len = count
+                    .checked_mul(4)
+                    .ok_or_else(|| invalid_data("too many voxels"))?;
+                let voxels = content.bytes(len)?;
+                models.push(
+                    voxels
+                        .chunks(4)
+                        .map(|voxel| IVec3::new(voxel[0] as i32, voxel[1] as i32, voxel[2] as i32))
+                        .collect(),
+                );
+            }
+            b"nTRN" => {
+                content.i32()?;
+                content.dict()?;
+                let child = content.i32()?;
+                content.i32()?;
+                content.i32()?;
+                if content.i32()? > 0 {
+                    if let Some(t) = content.dict()?.get("_t") {
+                        let t: Vec<i32> = t
+                            .split_whitespace()
+                            .filter_map(|n| n.parse().ok())
+                            .collect();
+                        if let [x, y, z] = t[..] {
+                            translations.insert(child, IVec3::new(x, y, z));
+                        }
+                    }
+                }
+            }
+            b"nSHP" => {
+                let node = content.i32()?;
+                content.dict()?;
+                if content.i32()? > 0 {
+                    shape_models.insert(content.i32()?, node);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut cells = vec![];
+    for (model, (voxels, size)) in models.iter().zip(sizes).enumerate() {
+        let translation = match shape_models
+            .get(&(model as i32))
+            .and_then(|node| translations.get(node))
+        {
+            Some(center) => translate(*center, -(size / 2))?,
+            None => IVec3::ZERO,
+        };
+        for voxel in voxels {
+            let pos = translate(*voxel, translation)?;
+            // .vox is z-up, the world is y-up.
+            cells.push((IVec3::new(pos.x, pos.z, pos.y), states));
+        }
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn center_far_away_cells() {
+        let cells = vec![(IVec3::splat(i32::MAX), 1), (IVec3::splat(i32::MAX - 1), 1)];
+        assert_eq!(center(cells, 32).len(), 2);
+
+        let cells = vec![(IVec3::splat(i32::MIN), 1), (IVec3::splat(i32::MAX), 1)];
+        assert!(center(cells, 32).is_empty());
+    }
+}
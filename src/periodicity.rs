@@ -0,0 +1,149 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use bevy::prelude::*;
+
+use crate::{cell_renderer::CellRenderer, utils};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Periodicity {
+    Dead,
+    StillLife,
+    Oscillator { period: u64 },
+    Spaceship { period: u64, displacement: IVec3 },
+}
+
+impl fmt::Display for Periodicity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Periodicity::Dead => write!(f, "dead"),
+            Periodicity::StillLife => write!(f, "still life"),
+            Periodicity::Oscillator { period } => write!(f, "oscillator period {}", period),
+            Periodicity::Spaceship {
+                period,
+                displacement: d,
+            } => write!(
+                f,
+                "spaceship period {} with displacement ({},{},{})",
+                period, d.x, d.y, d.z
+            ),
+        }
+    }
+}
+
+// remembers a hash of every recent generation. the cells are hashed relative
+// to their bounding box so a translated copy hashes the same, the box corner
+// then tells oscillators and spaceships apart. records may be several
+// generations apart, so periods are measured in generations and a still life
+// is a world that didn't change since the previous record.
+pub struct PeriodDetector {
+    capacity: usize,
+    seen: HashMap<u64, (u64, IVec3)>,
+    order: VecDeque<u64>,
+    previous: Option<u64>,
+    result: Option<Periodicity>,
+}
+
+impl PeriodDetector {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+            previous: None,
+            result: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+        self.previous = None;
+        self.result = None;
+    }
+
+    pub fn result(&self) -> Option<Periodicity> {
+        self.result
+    }
+
+    pub fn record(&mut self, generation: u64, renderer: &CellRenderer) {
+        let cells: Vec<(IVec3, u8)> = renderer
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != 0)
+            .map(|(idx, value)| (utils::idx_to_pos(idx, renderer.bounds), *value))
+            .collect();
+        let min = match utils::bounding_box(cells.iter().map(|(pos, _)| *pos)) {
+            Some((min, _)) => min,
+            None => {
+                self.result = Some(Periodicity::Dead);
+                self.previous = None;
+                return;
+            }
+        };
+        let mut hasher = DefaultHasher::new();
+        for (pos, value) in cells.iter() {
+            (*pos - min).to_array().hash(&mut hasher);
+            value.hash(&mut hasher);
+        }
+        let hash = hasher.finish();
+
+        self.result = match self.seen.get(&hash) {
+            Some((seen, _)) if *seen >= generation => None,
+            Some((seen, seen_min)) => {
+                let period = generation - seen;
+                let displacement = min - *seen_min;
+                Some(if displacement != IVec3::ZERO {
+                    Periodicity::Spaceship {
+                        period,
+                        displacement,
+                    }
+                } else if self.previous == Some(hash) {
+                    Periodicity::StillLife
+                } else {
+                    Periodicity::Oscillator { period }
+                })
+            }
+            None => None,
+        };
+
+        // a hash seen again moves to the back so eviction drops the entries
+        // that really are the oldest.
+        if self.seen.insert(hash, (generation, min)).is_some() {
+            if let Some(idx) = self.order.iter().position(|seen| *seen == hash) {
+                self.order.remove(idx);
+            }
+        }
+        self.order.push_back(hash);
+        self.previous = Some(hash);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // hashlife records a generation every 2^k steps, which must not make a
+    // still life look like an oscillator.
+    #[test]
+    fn still_life_across_steps() {
+        let mut renderer = CellRenderer::new();
+        renderer.set_bounds(8);
+        renderer.set(utils::pos_to_idx(IVec3::splat(3), 8), 1, 0);
+
+        let mut detector = PeriodDetector::new(16);
+        detector.record(0, &renderer);
+        assert!(detector.result().is_none());
+        detector.record(8, &renderer);
+        assert!(detector.result() == Some(Periodicity::StillLife));
+    }
+}
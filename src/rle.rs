@@ -0,0 +1,317 @@
+use bevy::prelude::*;
+
+use crate::{cells::MAX_BOUNDS, rule::Rule, utils};
+
+// Golly's RLE extended to 3D:
+//
+//   x = 3, y = 2, z = 2, rule = 4/4/5/M
+//   3o$obo/2o$bo!
+//
+// `$` ends a row and `/` ends a layer. 2 state patterns use `b` for dead
+// and `o` for live cells, others use `.` and the multi-state letters
+// `A`..`X`, `pA`..`yO` for states 1 to 255.
+const MAX_LINE: usize = 70;
+
+pub struct Pattern {
+    pub rule: Option<Rule>,
+    pub size: IVec3,
+    pub cells: Vec<(IVec3, u8)>,
+}
+
+impl Pattern {
+    // moves the cells so their bounding box starts at the origin.
+    pub fn new(rule: Option<Rule>, cells: &[(IVec3, u8)]) -> Self {
+        let (min, max) = match utils::bounding_box(cells.iter().map(|(pos, _)| *pos)) {
+            Some(bounding_box) => bounding_box,
+            None => {
+                return Self {
+                    rule,
+                    size: IVec3::ZERO,
+                    cells: vec![],
+                }
+            }
+        };
+        Self {
+            rule,
+            size: max - min + 1,
+            cells: cells
+                .iter()
+                .filter(|(_, value)| *value != 0)
+                .map(|(pos, value)| (*pos - min, *value))
+                .collect(),
+        }
+    }
+
+    pub fn to_rle(&self) -> String {
+        let two_state = self
+            .cells
+            .iter()
+            .all(|(_, value)| self.rule.as_ref().is_some_and(|rule| *value == rule.states));
+        let dead = if two_state { "b" } else { "." };
+
+        let size = self.size;
+        let mut grid = vec![0; (size.x * size.y * size.z) as usize];
+        for (pos, value) in self.cells.iter() {
+            grid[(pos.x + (pos.y + pos.z * size.y) * size.x) as usize] = *value;
+        }
+
+        let mut symbols: Vec<String> = vec![];
+        for (z, layer) in grid
+            .chunks(size.x.max(1) as usize * size.y.max(1) as usize)
+            .enumerate()
+        {
+            if z > 0 {
+                symbols.push("/".into());
+            }
+
+            let rows: Vec<&[u8]> = layer.chunks(size.x.max(1) as usize).collect();
+            let used_rows = rows
+                .iter()
+                .rposition(|row| row.iter().any(|value| *value != 0));
+            for (y, row) in rows
+                .iter()
+                .take(used_rows.map_or(0, |last| last + 1))
+                .enumerate()
+            {
+                if y > 0 {
+                    symbols.push("$".into());
+                }
+                let used = row
+                    .iter()
+                    .rposition(|value| *value != 0)
+                    .map_or(0, |last| last + 1);
+                for value in row[..used].iter() {
+                    symbols.push(match *value {
+                        0 => dead.into(),
+                        _ if two_state => "o".into(),
+                        value => state_symbol(value),
+                    });
+                }
+            }
+        }
+        symbols.push("!".into());
+
+        let mut runs: Vec<String> = vec![];
+        let mut symbols = symbols.into_iter().peekable();
+        while let Some(symbol) = symbols.next() {
+            let mut count = 1;
+            while symbols.peek() == Some(&symbol) {
+                symbols.next();
+                count += 1;
+            }
+            runs.push(if count > 1 {
+                format!("{}{}", count, symbol)
+            } else {
+                symbol
+            });
+        }
+
+        let mut rle = format!("x = {}, y = {}, z = {}", size.x, size.y, size.z);
+        if let Some(rule) = &self.rule {
+            rle += &format!(", rule = {}", rule);
+        }
+        rle.push('\n');
+
+        let mut line_len = 0;
+        for run in runs {
+            if line_len + run.len() > MAX_LINE {
+                rle.push('\n');
+                line_len = 0;
+            }
+            line_len += run.len();
+            rle += &run;
+        }
+        rle.push('\n');
+        rle
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let header = lines.next().ok_or("empty pattern")?;
+
+        // the rule string has commas of its own, so it always comes last.
+        let (sizes, rule) = match header.find("rule") {
+            Some(idx) => {
+                let rule = header[idx + "rule".len()..].trim_start();
+                let rule = rule.strip_prefix('=').ok_or("expected `rule = ...`")?;
+                (&header[..idx], Some(rule.parse::<Rule>()?))
+            }
+            None => (header, None),
+        };
+
+        let mut size = IVec3::ZERO;
+        for entry in sizes
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("invalid header entry `{}`", entry))?;
+            let value: i32 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid size `{}`", entry))?;
+            if !(0..=MAX_BOUNDS).contains(&value) {
+                return Err(format!("size `{}` outside of 0..={}", entry, MAX_BOUNDS));
+            }
+            match key.trim() {
+                "x" => size.x = value,
+                "y" => size.y = value,
+                "z" => size.z = value,
+                key => return Err(format!("unknown header key `{}`", key)),
+            }
+        }
+
+        // runs may not leave the declared size, or the largest world for axes
+        // the header leaves out.
+        let limit = IVec3::select(size.cmpgt(IVec3::ZERO), size, IVec3::splat(MAX_BOUNDS));
+        let advance = |from: i32, run: i32, limit: i32| {
+            from.checked_add(run)
+                .filter(|to| *to <= limit)
+                .ok_or_else(|| "pattern runs past its size".to_string())
+        };
+
+        let live = rule.as_ref().map_or(1, |rule| rule.states);
+        let mut cells = vec![];
+        let mut pos = IVec3::ZERO;
+        let mut count = None;
+        let mut prefix = None;
+        for c in lines.flat_map(str::chars).filter(|c| !c.is_whitespace()) {
+            if let Some(digit) = c.to_digit(10) {
+                count = Some(
+                    count
+                        .unwrap_or(0_i32)
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit as i32))
+                        .ok_or("run count too large")?,
+                );
+                continue;
+            }
+
+            let run = count.take().unwrap_or(1);
+            let value = match c {
+                'b' | '.' => 0,
+                'o' => live,
+                'A'..='X' => {
+                    let offset = prefix
+                        .take()
+                        .map_or(0, |p: char| (p as u32 - 'p' as u32 + 1) * 24);
+                    (offset + c as u32 - 'A' as u32 + 1)
+                        .try_into()
+                        .map_err(|_| format!("state `{}` out of range", c))?
+                }
+                'p'..='y' => {
+                    prefix = Some(c);
+                    count = Some(run).filter(|run| *run > 1);
+                    continue;
+                }
+                '$' => {
+                    pos = IVec3::new(0, advance(pos.y, run, limit.y)?, pos.z);
+                    continue;
+                }
+                '/' => {
+                    pos = IVec3::new(0, 0, advance(pos.z, run, limit.z)?);
+                    continue;
+                }
+                '!' => break,
+                c => return Err(format!("unexpected `{}` in pattern", c)),
+            };
+
+            let end = advance(pos.x, run, limit.x)?;
+            if pos.y >= limit.y || pos.z >= limit.z {
+                return Err("pattern runs past its size".into());
+            }
+            if value != 0 {
+                cells.extend((pos.x..end).map(|x| (IVec3::new(x, pos.y, pos.z), value)));
+            }
+            pos.x = end;
+        }
+
+        Ok(Self { rule, size, cells })
+    }
+}
+
+fn state_symbol(value: u8) -> String {
+    let letter = |idx: u8| char::from(b'A' + idx);
+    if value <= 24 {
+        letter(value - 1).to_string()
+    } else {
+        let prefix = char::from(b'p' + (value - 25) / 24);
+        format!("{}{}", prefix, letter((value - 25) % 24))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an 8x8x6 block with a gap every few cells, values cycling through
+    // `values`.
+    fn cells(values: &[u8]) -> Vec<(IVec3, u8)> {
+        let mut cells = vec![];
+        let mut i = 0;
+        for z in 0..6 {
+            for y in 0..8 {
+                for x in 0..8 {
+                    if (x + y * 2 + z * 3) % 4 != 0 {
+                        cells.push((IVec3::new(x, y, z), values[i % values.len()]));
+                        i += 1;
+                    }
+                }
+            }
+        }
+        cells
+    }
+
+    fn round_trip(rule: &str, cells: &[(IVec3, u8)]) -> String {
+        let pattern = Pattern::new(Some(rule.parse().unwrap()), cells);
+        let text = pattern.to_rle();
+        let parsed = Pattern::parse(&text).unwrap();
+
+        let mut expected = pattern.cells.clone();
+        let mut actual = parsed.cells.clone();
+        expected.sort_by_key(|(pos, _)| pos.to_array());
+        actual.sort_by_key(|(pos, _)| pos.to_array());
+        assert!(parsed.rule == pattern.rule);
+        assert_eq!(parsed.size, pattern.size);
+        assert_eq!(actual, expected);
+        text
+    }
+
+    #[test]
+    fn two_states() {
+        let text = round_trip("4/4/5/M", &cells(&[5]));
+        assert!(text.contains('o') && !text.contains('A'));
+    }
+
+    #[test]
+    fn multi_state() {
+        let values: Vec<u8> = (1..=24).collect();
+        let text = round_trip("4/4/24/M", &cells(&values));
+        assert!(text.contains('A') && text.contains('X'));
+    }
+
+    #[test]
+    fn prefixed_states() {
+        let values: Vec<u8> = (1..=255).rev().collect();
+        let text = round_trip("4/4/255/M", &cells(&values));
+        assert!(text.contains("pA") && text.contains("yO"));
+    }
+
+    #[test]
+    fn overflowing_count() {
+        assert!(Pattern::parse("x = 3, y = 1, z = 1\n99999999999o!").is_err());
+    }
+
+    #[test]
+    fn oversized_run() {
+        assert!(Pattern::parse("x = 3, y = 1, z = 1\n999999999o!").is_err());
+        assert!(Pattern::parse("x = 3, y = 1, z = 1\n2o999999999$o!").is_err());
+        assert!(Pattern::parse("rule = 4/4/2/M\n200o!").is_err());
+        assert!(Pattern::parse("rule = 4/4/2/M\n128o!").is_ok());
+    }
+}
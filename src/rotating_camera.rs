@@ -1,40 +1,192 @@
-use bevy::prelude::*;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy::render::camera::Projection;
+use bevy_egui::{egui, EguiContext};
+use bevy_flycam::{FlyCam, NoCameraPlayerPlugin};
 
+use crate::cells::Sims;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    Orbit,
+    Fly,
+}
+
+// orbits `center` at `dist`, dragging with the left mouse button rotates,
+// the right one pans and scrolling zooms. ctrl is left for the brush.
 #[derive(Component)]
 pub struct RotatingCamera {
     pub rotation: f32,
-    pub last_tick: f32,
+    pub pitch: f32,
+    // radians per second while auto-rotating.
     pub speed: f32,
+    pub auto_rotate: bool,
     pub dist: f32,
     pub center: Vec3,
+    pub mode: CameraMode,
+    // keeps the live cells centered and in view, overriding pan and zoom.
+    pub follow: bool,
 }
 
 impl Default for RotatingCamera {
     fn default() -> Self {
         Self {
             rotation: 0.0,
-            last_tick: 0.0,
-            speed: 0.01,
+            pitch: 0.0,
+            speed: 0.6,
+            auto_rotate: true,
             dist: 150.0,
             center: vec3(0.0, 0.0, 0.0),
+            mode: CameraMode::Orbit,
+            follow: false,
         }
     }
 }
 
+const ROTATE_SENSITIVITY: f32 = 0.005;
+const PAN_SENSITIVITY: f32 = 0.0015;
+const ZOOM_SENSITIVITY: f32 = 0.1;
+// how quickly a following camera catches up, per second.
+const FOLLOW_RATE: f32 = 2.0;
+
 pub struct RotatingCameraPlugin;
 impl Plugin for RotatingCameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(update_tick);
+        app.add_plugin(NoCameraPlayerPlugin)
+            .add_system(camera_ui)
+            .add_system(update_tick);
     }
 }
 
-pub fn update_tick(mut cameras: Query<(&mut RotatingCamera, &mut Transform)>) {
-    for (mut camera, mut transform) in cameras.iter_mut() {
-        let delta = 1.0f32;
-        camera.rotation += delta * camera.speed;
-        let rotation = Quat::from_axis_angle(Vec3::Y, camera.rotation);
+fn set_cursor_grab(window: &mut Window, grab: bool) {
+    window.set_cursor_lock_mode(grab);
+    window.set_cursor_visibility(!grab);
+}
+
+pub fn camera_ui(
+    mut commands: Commands,
+    mut egui_ctx: ResMut<EguiContext>,
+    keys: Res<Input<KeyCode>>,
+    mut windows: ResMut<Windows>,
+    mut cameras: Query<(Entity, &mut RotatingCamera)>,
+) {
+    for (entity, mut camera) in cameras.iter_mut() {
+        let old_mode = camera.mode;
+        egui::Window::new("Camera").show(egui_ctx.ctx_mut(), |ui| {
+            ui.checkbox(&mut camera.auto_rotate, "auto-rotate (R)");
+            ui.add(egui::Slider::new(&mut camera.speed, -3.0..=3.0).text("rotation speed"));
+            ui.add(egui::Slider::new(&mut camera.dist, 5.0..=1000.0).text("distance"));
+            ui.checkbox(&mut camera.follow, "follow population");
+            if ui.button("recenter").clicked() {
+                camera.center = Vec3::ZERO;
+            }
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut camera.mode, CameraMode::Orbit, "orbit");
+                ui.selectable_value(&mut camera.mode, CameraMode::Fly, "fly (F)");
+            });
+            if camera.mode == CameraMode::Fly {
+                ui.label("WASD, space and shift move, escape frees the cursor");
+            }
+        });
+
+        if !egui_ctx.ctx_mut().wants_keyboard_input() {
+            if keys.just_pressed(KeyCode::R) {
+                camera.auto_rotate = !camera.auto_rotate;
+            }
+            if keys.just_pressed(KeyCode::F) {
+                camera.mode = match camera.mode {
+                    CameraMode::Orbit => CameraMode::Fly,
+                    CameraMode::Fly => CameraMode::Orbit,
+                };
+            }
+        }
+
+        let grab = camera.mode == CameraMode::Fly;
+        if camera.mode != old_mode {
+            if grab {
+                commands.entity(entity).insert(FlyCam);
+            } else {
+                commands.entity(entity).remove::<FlyCam>();
+            }
+        }
+
+        // the flycam plugin grabs the cursor on startup and on escape, which
+        // only makes sense while flying.
+        if let Some(window) = windows.get_primary_mut() {
+            if camera.mode != old_mode || (!grab && window.cursor_locked()) {
+                set_cursor_grab(window, grab);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_tick(
+    time: Res<Time>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut egui_ctx: ResMut<EguiContext>,
+    sims: Res<Sims>,
+    mut cameras: Query<(&mut RotatingCamera, &mut Transform, Option<&Projection>)>,
+) {
+    let ctx = egui_ctx.ctx_mut();
+    let use_mouse = !ctx.wants_pointer_input()
+        && !ctx.is_pointer_over_area()
+        && !keys.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    let drag = motion
+        .iter()
+        .fold(Vec2::ZERO, |drag, event| drag + event.delta);
+    let scroll: f32 = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+
+    for (mut camera, mut transform, projection) in cameras.iter_mut() {
+        if camera.mode != CameraMode::Orbit {
+            continue;
+        }
+
+        if let Some(population) = sims.population().filter(|_| camera.follow) {
+            if population.count > 0 {
+                let radius = population.radius.max(4.0);
+                let fov = match projection {
+                    Some(Projection::Perspective(perspective)) => perspective.fov,
+                    _ => std::f32::consts::FRAC_PI_4,
+                };
+                let blend = 1.0 - (-FOLLOW_RATE * time.delta_seconds()).exp();
+                camera.center = camera.center.lerp(population.center_of_mass, blend);
+                let dist = radius / (fov / 2.0).sin();
+                camera.dist += (dist - camera.dist) * blend;
+            }
+        }
+
+        if camera.auto_rotate {
+            camera.rotation += camera.speed * time.delta_seconds();
+        }
+
+        if use_mouse {
+            if mouse.pressed(MouseButton::Left) {
+                camera.rotation -= drag.x * ROTATE_SENSITIVITY;
+                camera.pitch = (camera.pitch - drag.y * ROTATE_SENSITIVITY).clamp(-1.5, 1.5);
+            }
+            if mouse.pressed(MouseButton::Right) {
+                let pan = (transform.up() * drag.y - transform.right() * drag.x)
+                    * camera.dist
+                    * PAN_SENSITIVITY;
+                camera.center += pan;
+            }
+            camera.dist = (camera.dist * (1.0 - scroll * ZOOM_SENSITIVITY)).clamp(5.0, 1000.0);
+        }
+
+        let rotation = Quat::from_axis_angle(Vec3::Y, camera.rotation)
+            * Quat::from_axis_angle(Vec3::X, -camera.pitch);
         transform.translation = camera.center + (rotation * Vec3::Z * camera.dist);
-        transform.look_at(camera.center,Vec3::Y);
+        transform.look_at(camera.center, Vec3::Y);
     }
-}
\ No newline at end of file
+}
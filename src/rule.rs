@@ -1,4 +1,4 @@
-use std::ops::RangeInclusive;
+use std::{fmt, ops::RangeInclusive, str::FromStr};
 
 use crate::neighbors::NeighborMethod;
 
@@ -10,6 +10,22 @@ pub struct Rule {
     pub neighbor_method: NeighborMethod,
 }
 
+impl Rule {
+    pub fn next_state(&self, value: u8, neighbors: u8) -> u8 {
+        if value == 0 {
+            if self.birth_rule.in_range(neighbors) {
+                self.states
+            } else {
+                0
+            }
+        } else if value == self.states && self.survival_rule.in_range(neighbors) {
+            value
+        } else {
+            value - 1
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub struct Value ([bool; 27]);
 impl Value {
@@ -28,4 +44,105 @@ impl Value {
         }
         result
     }
+
+    pub fn in_range(&self, value: u8) -> bool {
+        self.0[value as usize]
+    }
+
+    pub fn set(&mut self, value: u8, enabled: bool) {
+        self.0[value as usize] = enabled;
+    }
+
+    pub fn indices(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..27).filter(|idx| self.0[*idx as usize])
+    }
+}
+
+// rules are written `survival/birth/states/neighbors`, e.g. `4/4/5/M` or
+// `9-26/5-7,12-13,15/20/M`, with `M` for Moore and `VN` for von Neumann.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ranges: Vec<(u8, u8)> = vec![];
+        for idx in self.indices() {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == idx => *end = idx,
+                _ => ranges.push((idx, idx)),
+            }
+        }
+
+        let ranges: Vec<String> = ranges
+            .into_iter()
+            .map(|(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{}-{}", start, end)
+                }
+            })
+            .collect();
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+impl FromStr for Value {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut result = Value([false; 27]);
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            let parse = |value: &str| match value.trim().parse::<usize>() {
+                Ok(value) if value < 27 => Ok(value),
+                _ => Err(format!("invalid neighbor count `{}`", value)),
+            };
+            for idx in parse(start)?..=parse(end)? {
+                result.0[idx] = true;
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let neighbors = match self.neighbor_method {
+            NeighborMethod::Moore => "M",
+            NeighborMethod::VonNeumann => "VN",
+        };
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            self.survival_rule, self.birth_rule, self.states, neighbors
+        )
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('/').collect();
+        if parts.len() != 4 {
+            return Err(format!(
+                "expected `survival/birth/states/neighbors`, got `{}`",
+                s
+            ));
+        }
+
+        let neighbor_method = match parts[3].trim() {
+            "M" => NeighborMethod::Moore,
+            "VN" => NeighborMethod::VonNeumann,
+            other => return Err(format!("unknown neighbor method `{}`", other)),
+        };
+
+        Ok(Rule {
+            survival_rule: parts[0].parse()?,
+            birth_rule: parts[1].parse()?,
+            states: match parts[2].trim().parse::<u8>() {
+                Ok(states) if states > 0 => states,
+                _ => return Err(format!("invalid state count `{}`", parts[2])),
+            },
+            neighbor_method,
+        })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,230 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    cells::MAX_BOUNDS,
+    rule::Rule,
+    utils::{self, invalid_data},
+};
+
+const MAGIC: &[u8; 4] = b"RSAW";
+const VERSION: u8 = 1;
+// longer rules are taken for a corrupt file rather than allocated.
+const MAX_RULE_LEN: u64 = 256;
+
+pub struct Snapshot {
+    pub bounds: i32,
+    pub rule: Rule,
+    pub generation: u64,
+    pub cells: Vec<(IVec3, u8)>,
+}
+
+// a world larger than the ui offers would overflow the cell count of every
+// simulator, so such a file is treated as corrupt.
+fn check_bounds(bounds: i64) -> io::Result<i32> {
+    if (1..=MAX_BOUNDS as i64).contains(&bounds) {
+        Ok(bounds as i32)
+    } else {
+        Err(invalid_data(format!(
+            "bounds {} outside of 1..={}",
+            bounds, MAX_BOUNDS
+        )))
+    }
+}
+
+fn write_varint(w: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(r: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint too long"))
+}
+
+impl Snapshot {
+    // .txt files are written as text, anything else as binary.
+    fn is_text(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "txt")
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        if Self::is_text(path) {
+            self.write_text(&mut w)?;
+        } else {
+            self.write_binary(&mut w)?;
+        }
+        w.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+        if Self::is_text(path) {
+            Self::read_text(&mut r)
+        } else {
+            Self::read_binary(&mut r)
+        }
+    }
+
+    // cells are stored sorted by index, as the gap to the previous cell
+    // followed by the value, so both sparse and dense worlds stay small.
+    pub fn write_binary(&self, w: &mut impl Write) -> io::Result<()> {
+        let rule = self.rule.to_string();
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        write_varint(w, self.bounds as u64)?;
+        write_varint(w, rule.len() as u64)?;
+        w.write_all(rule.as_bytes())?;
+        write_varint(w, self.generation)?;
+
+        let mut indices: Vec<(usize, u8)> = self
+            .cells
+            .iter()
+            .map(|(pos, value)| (utils::pos_to_idx(*pos, self.bounds), *value))
+            .collect();
+        indices.sort_unstable();
+
+        write_varint(w, indices.len() as u64)?;
+        let mut last = 0;
+        for (idx, value) in indices {
+            write_varint(w, (idx - last) as u64)?;
+            w.write_all(&[value])?;
+            last = idx;
+        }
+        Ok(())
+    }
+
+    pub fn read_binary(r: &mut impl Read) -> io::Result<Self> {
+        let mut header = [0; 5];
+        r.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("not a snapshot file"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported snapshot version {}",
+                header[4]
+            )));
+        }
+
+        let bounds = check_bounds(read_varint(r)?.try_into().unwrap_or(i64::MAX))?;
+        let rule_len = read_varint(r)?;
+        if rule_len > MAX_RULE_LEN {
+            return Err(invalid_data("rule too long"));
+        }
+        let mut rule = vec![0; rule_len as usize];
+        r.read_exact(&mut rule)?;
+        let rule = String::from_utf8(rule)
+            .map_err(|_| invalid_data("rule is not utf-8"))?
+            .parse()
+            .map_err(invalid_data)?;
+        let generation = read_varint(r)?;
+
+        let cell_count = bounds.pow(3) as usize;
+        let count = read_varint(r)? as usize;
+        let mut cells = Vec::with_capacity(count.min(cell_count));
+        let mut idx: usize = 0;
+        for _ in 0..count {
+            idx = idx.saturating_add(read_varint(r)? as usize);
+            if idx >= cell_count {
+                return Err(invalid_data("cell outside of bounds"));
+            }
+            let mut value = [0];
+            r.read_exact(&mut value)?;
+            cells.push((utils::idx_to_pos(idx, bounds), value[0]));
+        }
+
+        Ok(Self {
+            bounds,
+            rule,
+            generation,
+            cells,
+        })
+    }
+
+    pub fn write_text(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "# rs-automata snapshot")?;
+        writeln!(w, "bounds {}", self.bounds)?;
+        writeln!(w, "rule {}", self.rule)?;
+        writeln!(w, "generation {}", self.generation)?;
+        writeln!(w, "# x y z value")?;
+        for (pos, value) in self.cells.iter() {
+            writeln!(w, "{} {} {} {}", pos.x, pos.y, pos.z, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_text(r: &mut impl BufRead) -> io::Result<Self> {
+        let mut bounds = None;
+        let mut rule = None;
+        let mut generation = 0;
+        let mut cells = vec![];
+
+        for line in r.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "bounds" => {
+                    let value = value.parse().map_err(|_| invalid_data("invalid bounds"))?;
+                    bounds = Some(check_bounds(value)?);
+                }
+                "rule" => rule = Some(value.parse().map_err(invalid_data)?),
+                "generation" => {
+                    generation = value
+                        .parse()
+                        .map_err(|_| invalid_data("invalid generation"))?
+                }
+                _ => cells.push(parse_cell(line)?),
+            }
+        }
+
+        Ok(Self {
+            bounds: bounds.ok_or_else(|| invalid_data("missing bounds"))?,
+            rule: rule.ok_or_else(|| invalid_data("missing rule"))?,
+            generation,
+            cells,
+        })
+    }
+}
+
+pub(crate) fn parse_cell(line: &str) -> io::Result<(IVec3, u8)> {
+    let numbers: Vec<i32> = line
+        .split_whitespace()
+        .map(|number| number.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid_data(format!("invalid cell `{}`", line)))?;
+
+    match numbers[..] {
+        [x, y, z, value] if (0..=255).contains(&value) => Ok((IVec3::new(x, y, z), value as u8)),
+        _ => Err(invalid_data(format!(
+            "expected `x y z value`, got `{}`",
+            line
+        ))),
+    }
+}
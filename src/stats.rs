@@ -0,0 +1,208 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use bevy::prelude::*;
+use bevy_egui::egui::{
+    self,
+    plot::{Bar, BarChart, Legend, Line, Plot, Value, Values},
+};
+
+use crate::{cell_renderer::CellRenderer, utils};
+
+pub struct Sample {
+    pub generation: u64,
+    pub live: usize,
+    pub births: usize,
+    pub deaths: usize,
+    // number of cells in each state, index 0 is unused.
+    pub histogram: Vec<usize>,
+    // size of the bounding box around the live cells.
+    pub extent: IVec3,
+}
+
+pub struct Stats {
+    capacity: usize,
+    samples: VecDeque<Sample>,
+    values: Vec<u8>,
+    csv_path: String,
+    status: Option<String>,
+}
+
+impl Stats {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: VecDeque::new(),
+            values: vec![],
+            csv_path: "stats.csv".into(),
+            status: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.values.clear();
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    pub fn record(&mut self, generation: u64, states: u8, renderer: &CellRenderer) {
+        // births and deaths are only known relative to a previous sample.
+        let has_previous = self.values.len() == renderer.values.len();
+
+        let mut sample = Sample {
+            generation,
+            live: 0,
+            births: 0,
+            deaths: 0,
+            histogram: vec![0; states as usize + 1],
+            extent: IVec3::ZERO,
+        };
+        // a single pass over the renderer counts the sample and yields the live
+        // positions for the bounding box.
+        let previous = &self.values;
+        let live = renderer.values.iter().enumerate().filter_map(|(idx, value)| {
+            if has_previous {
+                match (previous[idx], *value) {
+                    (0, 1..) => sample.births += 1,
+                    (1.., 0) => sample.deaths += 1,
+                    _ => {}
+                }
+            }
+            if *value == 0 {
+                return None;
+            }
+
+            sample.live += 1;
+            if let Some(count) = sample.histogram.get_mut(*value as usize) {
+                *count += 1;
+            }
+            Some(utils::idx_to_pos(idx, renderer.bounds))
+        });
+        if let Some((min, max)) = utils::bounding_box(live) {
+            sample.extent = max - min + 1;
+        }
+
+        self.values.clear();
+        self.values.extend_from_slice(&renderer.values);
+        self.samples.push_back(sample);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let states = self
+            .samples
+            .iter()
+            .map(|sample| sample.histogram.len().saturating_sub(1))
+            .max()
+            .unwrap_or(0);
+
+        write!(w, "generation,live,births,deaths,extent_x,extent_y,extent_z")?;
+        for state in 1..=states {
+            write!(w, ",state_{}", state)?;
+        }
+        writeln!(w)?;
+
+        for sample in self.samples.iter() {
+            write!(
+                w,
+                "{},{},{},{},{},{},{}",
+                sample.generation,
+                sample.live,
+                sample.births,
+                sample.deaths,
+                sample.extent.x,
+                sample.extent.y,
+                sample.extent.z
+            )?;
+            for state in 1..=states {
+                write!(w, ",{}", sample.histogram.get(state).copied().unwrap_or(0))?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_csv(&self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut w)?;
+        w.flush()
+    }
+
+    fn series(&self, f: impl Fn(&Sample) -> f64) -> Values {
+        Values::from_values_iter(
+            self.samples
+                .iter()
+                .map(|sample| Value::new(sample.generation as f64, f(sample))),
+        )
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("samples: {} / {}", self.samples.len(), self.capacity));
+        ui.add(egui::Slider::new(&mut self.capacity, 16..=4096).text("samples kept"));
+
+        ui.label("Population:");
+        Plot::new("population")
+            .height(120.0)
+            .include_y(0.0)
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(self.series(|s| s.live as f64)).name("live"));
+                plot_ui.line(Line::new(self.series(|s| s.births as f64)).name("births"));
+                plot_ui.line(Line::new(self.series(|s| s.deaths as f64)).name("deaths"));
+            });
+
+        ui.label("Extent:");
+        Plot::new("extent")
+            .height(100.0)
+            .include_y(0.0)
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(self.series(|s| s.extent.x as f64)).name("x"));
+                plot_ui.line(Line::new(self.series(|s| s.extent.y as f64)).name("y"));
+                plot_ui.line(Line::new(self.series(|s| s.extent.z as f64)).name("z"));
+            });
+
+        ui.label("States:");
+        let bars = self.latest().map_or(vec![], |sample| {
+            sample
+                .histogram
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(state, count)| Bar::new(state as f64, *count as f64).width(1.0))
+                .collect()
+        });
+        Plot::new("states")
+            .height(100.0)
+            .include_y(0.0)
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars));
+            });
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.csv_path);
+            if ui.button("export csv").clicked() {
+                let path = std::path::PathBuf::from(&self.csv_path);
+                self.status = Some(match self.save_csv(&path) {
+                    Ok(()) => format!("exported {}", path.display()),
+                    Err(err) => format!("exporting failed: {}", err),
+                });
+            }
+            if ui.button("clear").clicked() {
+                self.clear();
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+    }
+}
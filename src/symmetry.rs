@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{cell_renderer::CellRenderer, utils};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymmetryGroup {
+    None,
+    // mirrored along x, along x and z, or along all three axes.
+    Mirror1,
+    Mirror2,
+    Mirror3,
+    // quarter turns around the y axis.
+    Rotate4,
+    // every rotation and reflection of a cube.
+    Octahedral,
+}
+
+// maps an offset `o` to `signs[i] * o[axes[i]]` on every axis `i`.
+#[derive(Clone, Copy)]
+struct Transform {
+    axes: [usize; 3],
+    signs: [i32; 3],
+}
+
+impl Transform {
+    fn apply(&self, offset: IVec3) -> IVec3 {
+        IVec3::new(
+            self.signs[0] * offset[self.axes[0]],
+            self.signs[1] * offset[self.axes[1]],
+            self.signs[2] * offset[self.axes[2]],
+        )
+    }
+}
+
+const IDENTITY: [usize; 3] = [0, 1, 2];
+
+fn sign_flips(flippable: [bool; 3]) -> Vec<[i32; 3]> {
+    let mut result = vec![];
+    for bits in 0..8 {
+        let signs = [0, 1, 2].map(|axis| if bits & (1 << axis) != 0 { -1 } else { 1 });
+        if (0..3).all(|axis| flippable[axis] || signs[axis] == 1) {
+            result.push(signs);
+        }
+    }
+    result
+}
+
+impl SymmetryGroup {
+    pub const ALL: [SymmetryGroup; 6] = [
+        SymmetryGroup::None,
+        SymmetryGroup::Mirror1,
+        SymmetryGroup::Mirror2,
+        SymmetryGroup::Mirror3,
+        SymmetryGroup::Rotate4,
+        SymmetryGroup::Octahedral,
+    ];
+
+    fn transforms(&self) -> Vec<Transform> {
+        let mirrors = |flippable| {
+            sign_flips(flippable)
+                .into_iter()
+                .map(|signs| Transform {
+                    axes: IDENTITY,
+                    signs,
+                })
+                .collect()
+        };
+
+        match self {
+            SymmetryGroup::None => mirrors([false, false, false]),
+            SymmetryGroup::Mirror1 => mirrors([true, false, false]),
+            SymmetryGroup::Mirror2 => mirrors([true, false, true]),
+            SymmetryGroup::Mirror3 => mirrors([true, true, true]),
+            SymmetryGroup::Rotate4 => [
+                ([0, 1, 2], [1, 1, 1]),
+                ([2, 1, 0], [1, 1, -1]),
+                ([0, 1, 2], [-1, 1, -1]),
+                ([2, 1, 0], [-1, 1, 1]),
+            ]
+            .map(|(axes, signs)| Transform { axes, signs })
+            .to_vec(),
+            SymmetryGroup::Octahedral => {
+                let permutations = [
+                    [0, 1, 2],
+                    [0, 2, 1],
+                    [1, 0, 2],
+                    [1, 2, 0],
+                    [2, 0, 1],
+                    [2, 1, 0],
+                ];
+                permutations
+                    .iter()
+                    .flat_map(|axes| {
+                        sign_flips([true; 3])
+                            .into_iter()
+                            .map(|signs| Transform { axes: *axes, signs })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymmetryGroup::None => "none",
+            SymmetryGroup::Mirror1 => "mirror x",
+            SymmetryGroup::Mirror2 => "mirror x, z",
+            SymmetryGroup::Mirror3 => "mirror x, y, z",
+            SymmetryGroup::Rotate4 => "4-fold rotation",
+            SymmetryGroup::Octahedral => "octahedral",
+        }
+    }
+}
+
+// the usual noise around the center of the world, repeated for every
+// element of `group` so the seed has exactly that symmetry.
+pub fn noise(group: SymmetryGroup, bounds: i32, value: u8) -> Vec<(IVec3, u8)> {
+    let transforms = group.transforms();
+    let center = utils::center(bounds);
+    let amount = 12_i32.pow(3) / transforms.len() as i32;
+
+    let mut cells = HashSet::new();
+    utils::make_some_noise(IVec3::ZERO, 7, amount, |offset| {
+        for transform in transforms.iter() {
+            cells.insert(center + transform.apply(offset));
+        }
+    });
+    cells
+        .into_iter()
+        .filter(|pos| utils::in_bounds(*pos, bounds))
+        .map(|pos| (pos, value))
+        .collect()
+}
+
+pub fn live_cells(renderer: &CellRenderer) -> Vec<IVec3> {
+    renderer
+        .values
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| **value != 0)
+        .map(|(idx, _)| utils::idx_to_pos(idx, renderer.bounds))
+        .collect()
+}
+
+// share of cells whose mirror image is also alive, averaged over the x, y
+// and z planes through the center of the bounding box. 1.0 is perfectly
+// mirror symmetric, an empty world counts as 0.0.
+pub fn mirror_symmetry(cells: &[IVec3]) -> f32 {
+    let (min, max) = match utils::bounding_box(cells.iter().copied()) {
+        Some(bounding_box) => bounding_box,
+        None => return 0.0,
+    };
+    let set: HashSet<IVec3> = cells.iter().copied().collect();
+
+    let mut matched = 0;
+    for axis in 0..3 {
+        for pos in cells.iter() {
+            let mut mirrored = *pos;
+            mirrored[axis] = min[axis] + max[axis] - pos[axis];
+            if set.contains(&mirrored) {
+                matched += 1;
+            }
+        }
+    }
+    matched as f32 / (cells.len() * 3) as f32
+}
+
+// share of cells whose images under `group`, taken around the center of the
+// bounding box, are alive too. 1.0 is perfectly symmetric, an empty world
+// counts as 0.0.
+pub fn measure(group: SymmetryGroup, cells: &[IVec3]) -> f32 {
+    let transforms: Vec<Transform> = group.transforms().into_iter().skip(1).collect();
+    let (min, max) = match utils::bounding_box(cells.iter().copied()) {
+        Some(bounding_box) => bounding_box,
+        None => return 0.0,
+    };
+    if transforms.is_empty() {
+        return 1.0;
+    }
+
+    let set: HashSet<IVec3> = cells.iter().copied().collect();
+
+    // offsets are doubled so a center between two cells stays an integer.
+    let sum = min + max;
+    let mut matched = 0;
+    for transform in transforms.iter() {
+        for pos in cells.iter() {
+            let image = transform.apply(*pos * 2 - sum) + sum;
+            if image % 2 == IVec3::ZERO && set.contains(&(image / 2)) {
+                matched += 1;
+            }
+        }
+    }
+    matched as f32 / (cells.len() * transforms.len()) as f32
+}
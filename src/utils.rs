@@ -1,3 +1,5 @@
+use std::io;
+
 use bevy::{math::ivec3, prelude::*};
 use bevy_egui::egui;
 use rand::Rng;
@@ -9,11 +11,35 @@ pub(crate) fn lerp(c1: Color, c2: Color, dt: f32) -> Color {
     ((1.0 - dt) * c1 + dt * c2).into()
 }
 
+// smallest and largest coordinate on every axis, none without positions.
+pub(crate) fn bounding_box(positions: impl IntoIterator<Item = IVec3>) -> Option<(IVec3, IVec3)> {
+    positions.into_iter().fold(None, |bounds, pos| match bounds {
+        Some((min, max)) => Some((min.min(pos), max.max(pos))),
+        None => Some((pos, pos)),
+    })
+}
+
+pub(crate) fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
 pub(crate) fn center(bounds: i32) -> IVec3 {
     let center = bounds / 2;
     ivec3(center, center, center)
 }
 
+pub(crate) fn wrap(pos: IVec3, bounds: i32) -> IVec3 {
+    ivec3(
+        pos.x.rem_euclid(bounds),
+        pos.y.rem_euclid(bounds),
+        pos.z.rem_euclid(bounds),
+    )
+}
+
+pub(crate) fn in_bounds(pos: IVec3, bounds: i32) -> bool {
+    pos.cmpge(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(bounds)).all()
+}
+
 pub(crate) fn dist_to_center(cell_pos: IVec3, bounds: i32) -> f32 {
     let cell_pos = cell_pos - center(bounds);
     let max = bounds as f32 / 2.0;
@@ -24,7 +50,7 @@ pub(crate) fn make_some_noise_default<F: FnMut(IVec3)>(center: IVec3, f: F) {
     make_some_noise(center, 7, 12_i32.pow(3), f)
 }
 
-fn make_some_noise<F: FnMut(IVec3)>(center: IVec3, radius: i32, amount: i32, mut f: F) {
+pub(crate) fn make_some_noise<F: FnMut(IVec3)>(center: IVec3, radius: i32, amount: i32, mut f: F) {
     let mut rand = rand::thread_rng();
     (0..amount).for_each(|_| {
         f(center